@@ -1,4 +1,4 @@
-use super::{CommentMatch, Start, End, find_comments_impl};
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParseState {
@@ -95,9 +95,18 @@ impl Start for MultiBlanklineState {
     }
 }
 
+impl Pending for MultiBlanklineState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            MultiBlanklineState::NotInMultiBlankline => None,
+            MultiBlanklineState::InMultiBlankline(from) => Some(from)
+        }
+    }
+}
+
 fn do_action(action: ParseAction, mut blankline_state: MultiBlanklineState, 
             position: usize, mut matches: Vec<CommentMatch>) 
-    -> Result<(MultiBlanklineState, Vec<CommentMatch>), &'static str> {
+    -> Result<(MultiBlanklineState, Vec<CommentMatch>), ScanError> {
     match action {
         ParseAction::Nothing => {},
         ParseAction::MultiBlanklineStart => {
@@ -106,10 +115,11 @@ fn do_action(action: ParseAction, mut blankline_state: MultiBlanklineState,
         ParseAction::MultiBlanklineEnd => {
             match blankline_state {
                 MultiBlanklineState::NotInMultiBlankline => {
-                    return Err(" blankline parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("blankline"), position));
                 },
                 MultiBlanklineState::InMultiBlankline(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                    /* blank lines are not comments, the kind is never looked at */
+                    matches.push(CommentMatch{from, to: position, kind: CommentKind::Line});
                     blankline_state = MultiBlanklineState::NotInMultiBlankline;
                 }
             }
@@ -118,10 +128,14 @@ fn do_action(action: ParseAction, mut blankline_state: MultiBlanklineState,
     Ok((blankline_state, matches))
 }
 
-pub fn find_blanklines(input: &str) -> Result<Vec<CommentMatch>, &'static str> {
+pub fn find_blanklines(input: &str) -> Result<Vec<CommentMatch>, Error> {
     find_comments_impl(input, state_transition, do_action)
 }
 
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,7 +153,7 @@ mod tests {
     fn starts_with_blanklines() {
         let input = "\n\nhello world\n";
         let expected = Ok(vec![
-            CommentMatch { from: 0, to: 2}
+            CommentMatch { from: 0, to: 2, kind: CommentKind::Line }
         ]);
         let actual = find_blanklines(input);
         assert_eq!(expected, actual);
@@ -149,7 +163,7 @@ mod tests {
     fn normal_blanklines() {
         let input = "hello\n\n\n world\n";
         let expected = Ok(vec![
-            CommentMatch { from: 6, to: 8}
+            CommentMatch { from: 6, to: 8, kind: CommentKind::Line }
         ]);
         let actual = find_blanklines(input);
         assert_eq!(expected, actual);
@@ -159,7 +173,7 @@ mod tests {
     fn ends_with_blanklines() {
         let input = "hello world\n\n\n";
         let expected = Ok(vec![
-            CommentMatch { from: 12, to: 14}
+            CommentMatch { from: 12, to: 14, kind: CommentKind::Line }
         ]);
         let actual = find_blanklines(input);
         assert_eq!(expected, actual);
@@ -169,9 +183,9 @@ mod tests {
     fn multiple_blanklines() {
         let input = "\n\nhello\n\n\n\nworld\n\n\n";
         let expected = Ok(vec![
-            CommentMatch { from: 0, to: 2},
-            CommentMatch { from: 8, to: 11},
-            CommentMatch { from: 17, to: 19}
+            CommentMatch { from: 0, to: 2, kind: CommentKind::Line },
+            CommentMatch { from: 8, to: 11, kind: CommentKind::Line },
+            CommentMatch { from: 17, to: 19, kind: CommentKind::Line }
         ]);
         let actual = find_blanklines(input);
         assert_eq!(expected, actual);
@@ -181,12 +195,19 @@ mod tests {
     fn no_newline_in_string() {
         let input = "\n'string\"inner string\"\n\n\n\n'\n";
         let expected = Ok(vec![
-            CommentMatch { from: 0, to: 1 }
+            CommentMatch { from: 0, to: 1, kind: CommentKind::Line }
         ]);
         let actual = find_blanklines(input);
         assert_eq!(expected, actual);
     }
 
-
-    
+    #[test]
+    fn multibyte_offsets() {
+        let input = "ä\n\n\nö\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 3, to: 5, kind: CommentKind::Line }
+        ]);
+        let actual = find_blanklines(input);
+        assert_eq!(expected, actual);
+    }
 }
@@ -1,11 +1,18 @@
-use super::{CommentMatch, Start, End, find_comments_impl};
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParseState {
     Start,
     Normal,
     FirstSlash,
+    SecondSlash,
+    /// `///`, a doc comment unless a fourth slash follows
+    ThirdSlash,
     SingleLineComment,
+    /// `/*`, might be followed by the second star of a doc comment
+    MultiLineCommentStart,
+    /// `/**`, a doc comment unless it is `/**/` or another star follows
+    MultiLineDocStart,
     MultiLineComment,
     MultiLineCommentFinalStar,
     MultiLineCommentFinalSlash,
@@ -32,10 +39,13 @@ impl End for ParseState {
 enum ParseAction {
     Nothing,
     CommentMightStart,
-    CommentConfirmed,
+    CommentConfirmed(CommentKind),
+    /// the comment turns out to be of another kind, like `///` is a doc comment
+    CommentKindChanged(CommentKind),
     CommentDismissed,
     CommentEnds,
-    CommentEndsAndCommentMightStart
+    CommentEndsAndCommentMightStart,
+    CommentUnterminated
 }
 
 fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
@@ -54,16 +64,35 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
                 _       => (ParseState::Normal, ParseAction::Nothing) 
             },
             ParseState::FirstSlash => match c {
-                '/'     => (ParseState::SingleLineComment, ParseAction::CommentConfirmed),
-                '*'     => (ParseState::MultiLineComment, ParseAction::CommentConfirmed),
+                '/'     => (ParseState::SecondSlash, ParseAction::CommentConfirmed(CommentKind::Line)),
+                '*'     => (ParseState::MultiLineCommentStart, ParseAction::CommentConfirmed(CommentKind::Block)),
                 '"'     => (ParseState::StringDoubleQuotes, ParseAction::CommentDismissed),
                 '\''    => (ParseState::StringSingleQuotes, ParseAction::CommentDismissed),
                 _       => (ParseState::Normal, ParseAction::CommentDismissed)
             },
+            ParseState::SecondSlash => match c {
+                '/'     => (ParseState::ThirdSlash, ParseAction::CommentKindChanged(CommentKind::Doc)),
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
+                _       => (ParseState::SingleLineComment, ParseAction::Nothing)
+            },
+            ParseState::ThirdSlash => match c {
+                '/'     => (ParseState::SingleLineComment, ParseAction::CommentKindChanged(CommentKind::Line)),
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
+                _       => (ParseState::SingleLineComment, ParseAction::Nothing)
+            },
             ParseState::SingleLineComment => match c {
                 '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
                 _       => (ParseState::SingleLineComment, ParseAction::Nothing)   
             },
+            ParseState::MultiLineCommentStart => match c {
+                '*'     => (ParseState::MultiLineDocStart, ParseAction::Nothing),
+                _       => (ParseState::MultiLineComment, ParseAction::Nothing)
+            },
+            ParseState::MultiLineDocStart => match c {
+                '/'     => (ParseState::MultiLineCommentFinalSlash, ParseAction::Nothing),
+                '*'     => (ParseState::MultiLineCommentFinalStar, ParseAction::Nothing),
+                _       => (ParseState::MultiLineComment, ParseAction::CommentKindChanged(CommentKind::Doc))
+            },
             ParseState::MultiLineComment => match c {
                 '*'     => (ParseState::MultiLineCommentFinalStar, ParseAction::Nothing),
                 _       => (ParseState::MultiLineComment, ParseAction::Nothing)  
@@ -97,12 +126,12 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
         None => match from {
             ParseState::FirstSlash => 
                 (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::SingleLineComment => 
+            ParseState::SecondSlash | ParseState::ThirdSlash | ParseState::SingleLineComment => 
                 (ParseState::End, ParseAction::CommentEnds),
-            ParseState::MultiLineComment => 
-                (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::MultiLineCommentStart | ParseState::MultiLineDocStart | ParseState::MultiLineComment => 
+                (ParseState::End, ParseAction::CommentUnterminated),
             ParseState::MultiLineCommentFinalStar => 
-                (ParseState::End, ParseAction::CommentDismissed),
+                (ParseState::End, ParseAction::CommentUnterminated),
             ParseState::MultiLineCommentFinalSlash => 
                 (ParseState::End, ParseAction::CommentEnds),
             _ => 
@@ -112,10 +141,11 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 enum CommentState {
     NotInComment,
     MaybeInComment(usize),
-    InComment(usize)
+    InComment(usize, CommentKind)
 }
 
 impl Start for CommentState {
@@ -124,48 +154,76 @@ impl Start for CommentState {
     }
 }
 
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from, _) => Some(from)
+        }
+    }
+}
+
 fn do_action(action: ParseAction, mut comment_state: CommentState, 
             position: usize, mut matches: Vec<CommentMatch>) 
-    -> Result<(CommentState, Vec<CommentMatch>), &'static str> {
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
     match action {
         ParseAction::Nothing => {},
         ParseAction::CommentMightStart => {
             comment_state = CommentState::MaybeInComment(position);
         },
-        ParseAction::CommentConfirmed => {
+        ParseAction::CommentConfirmed(kind) => {
             match comment_state {
                 CommentState::MaybeInComment(from) => {
-                    comment_state = CommentState::InComment(from);
+                    comment_state = CommentState::InComment(from, kind);
                 },
                 _ => {
-                    // println!("{:?}", (&comment_state, &current_state, &next_state, &current_char, &position));
-                    return Err("c style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("c style"), position));
                 }
 
             }
         },
+        ParseAction::CommentKindChanged(kind) => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    comment_state = CommentState::InComment(from, kind);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("c style"), position));
+                }
+            }
+        },
         ParseAction::CommentDismissed => {
             comment_state = CommentState::NotInComment
         },
         ParseAction::CommentEnds => {
             match comment_state {
-                CommentState::InComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                CommentState::InComment(from, kind) => {
+                    matches.push(CommentMatch{from, to: position, kind});
                     comment_state = CommentState::NotInComment;
                 },
                 _ => {
-                    return Err("c style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("c style"), position));
                 }
             }
         },
         ParseAction::CommentEndsAndCommentMightStart => {
             match comment_state {
-                CommentState::InComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                CommentState::InComment(from, kind) => {
+                    matches.push(CommentMatch{from, to: position, kind});
                     comment_state = CommentState::MaybeInComment(position);
                 },
                 _ => {
-                    return Err("c style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("c style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("c style"), position));
                 }
             }
         }
@@ -173,10 +231,14 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
     Ok((comment_state, matches))
 }
 
-pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, &'static str> {
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
     find_comments_impl(input, state_transition, do_action)
 }
 
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,7 +256,7 @@ mod tests {
     fn normal_comment() {
         let input = "int main() { /* comment */ }";
         let expected = Ok(vec![
-            CommentMatch { from: 13, to: 26}
+            CommentMatch { from: 13, to: 26, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -204,8 +266,8 @@ mod tests {
     fn multiple_comments() {
         let input = "main()/* comment */\n/* comment */";
         let expected = Ok(vec![
-            CommentMatch { from: 6, to: 19 },
-            CommentMatch { from: 20, to: 33 }
+            CommentMatch { from: 6, to: 19, kind: CommentKind::Block },
+            CommentMatch { from: 20, to: 33, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -215,7 +277,7 @@ mod tests {
     fn line_comment() {
         let input = "main() // comment\n";
         let expected = Ok(vec![
-            CommentMatch { from: 7, to: 17 }
+            CommentMatch { from: 7, to: 17, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -224,7 +286,7 @@ mod tests {
     fn line_comment_no_newline() {
         let input = "main() // comment";
         let expected = Ok(vec![
-            CommentMatch { from: 7, to: 17 }
+            CommentMatch { from: 7, to: 17, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -234,7 +296,7 @@ mod tests {
     fn multiline_comment() {
         let input = "/* multi \nline\ncomment */";
         let expected = Ok(vec![
-            CommentMatch { from: 0, to: 25 }
+            CommentMatch { from: 0, to: 25, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -247,4 +309,39 @@ mod tests {
         let actual = find_comments(input);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn doc_comments() {
+        let input = "/// doc\n//// line\n///\n/** doc */\n/**/ /*** block */\n/*/ block */";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 7, kind: CommentKind::Doc },
+            CommentMatch { from: 8, to: 17, kind: CommentKind::Line },
+            CommentMatch { from: 18, to: 21, kind: CommentKind::Doc },
+            CommentMatch { from: 22, to: 32, kind: CommentKind::Doc },
+            CommentMatch { from: 33, to: 37, kind: CommentKind::Block },
+            CommentMatch { from: 38, to: 51, kind: CommentKind::Block },
+            CommentMatch { from: 52, to: 64, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn multibyte_offsets() {
+        let input = "/* ä */ \"ö\" // 日本\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 8, kind: CommentKind::Block },
+            CommentMatch { from: 14, to: 23, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let input = "main() /* comment *";
+        let expected = Err(ScanError::new(ErrorKind::UnterminatedComment, 7).locate(input));
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
 }
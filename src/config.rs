@@ -1,10 +1,13 @@
 extern crate clap;
+extern crate regex;
 
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use self::clap::ArgMatches;
-use super::CommentStyle;
+use self::regex::Regex;
+use super::{AppError, CommentKind, CommentStyle, Detector, SqlDialect, StyleSpec};
+use replace::Replacement;
 
 #[derive(Debug)]
 pub enum Input {
@@ -17,12 +20,12 @@ impl Input {
         Input::Standard(io::stdin())
     }
     fn file<P: AsRef<Path>>(path: P) -> io::Result<Input> {
-        Ok(Input::File(try!(fs::File::open(path))))
+        Ok(Input::File(fs::File::open(path)?))
     }
-    fn from_arg<P: AsRef<Path>>(arg: Option<P>) -> io::Result<Input> {
+    pub fn from_arg<P: AsRef<Path>>(arg: Option<P>) -> io::Result<Input> {
         Ok(match arg {
             None       => Input::stdin(),
-            Some(path) => try!(Input::file(path))
+            Some(path) => Input::file(path)?
         })
     }
 }
@@ -39,7 +42,8 @@ impl io::Read for Input {
 #[derive(Debug)]
 pub enum Output {
     Standard(io::Stdout),
-    File(fs::File)
+    File(fs::File),
+    Replace(Replacement)
 }
 
 impl Output {
@@ -47,14 +51,25 @@ impl Output {
         Output::Standard(io::stdout())
     }
     fn file<P: AsRef<Path>>(path: P) -> io::Result<Output> {
-        Ok(Output::File(try!(fs::File::create(path))))
+        Ok(Output::File(fs::File::create(path)?))
     }
-    fn from_arg<P: AsRef<Path>>(arg: Option<P>) -> io::Result<Output> {
+    pub fn from_arg<P: AsRef<Path>>(arg: Option<P>) -> io::Result<Output> {
         Ok(match arg {
             None       => Output::stdout(),
-            Some(path) => try!(Output::file(path))
+            Some(path) => Output::file(path)?
         })
     }
+    pub fn replace<P: AsRef<Path>>(path: P, backup_suffix: Option<&str>, preserve_times: bool)
+        -> io::Result<Output> {
+        Ok(Output::Replace(Replacement::new(path.as_ref(), backup_suffix, preserve_times)?))
+    }
+    /// Must be called once everything is written, replaces the original file if needed
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Replace(r) => r.commit(),
+            _ => Ok(())
+        }
+    }
 }
 
 impl io::Write for Output {
@@ -62,39 +77,225 @@ impl io::Write for Output {
         match *self {
             Output::Standard(ref mut s) => s.write(buf),
             Output::File(ref mut f)     => f.write(buf),
+            Output::Replace(ref mut r)  => r.write(buf),
         }
     }
     fn flush(&mut self) -> io::Result<()> {
         match *self {
             Output::Standard(ref mut s) => s.flush(),
             Output::File(ref mut f)     => f.flush(),
+            Output::Replace(ref mut r)  => r.flush(),
         }
     }
 }
 
+/// Where the results are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// the given file or stdout, only for a single input
+    Single(Option<PathBuf>),
+    /// the same relative path below this directory
+    Directory(PathBuf),
+    /// replace the input, keeping a backup with the suffix if given
+    InPlace(Option<String>)
+}
+
+/// How `--list` prints the comments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `path:line:col: text`
+    Text,
+    /// one array of objects
+    Json,
+    /// one object per line
+    Ndjson
+}
+
+/// What to do with the comments found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// write the input without comments
+    Strip,
+    /// only report the comments, `list` prints each one, `check` fails if there are any
+    Report { list: bool, check: bool },
+    /// write only the comments, `prefix` each line with the file name and line number
+    Extract { trim_delimiters: bool, prefix: bool }
+}
+
 pub struct Config {
-    pub input: Input,
-    pub output: Output,
-    pub style: CommentStyle,
-    pub remove_blanks: bool
+    pub mode: Mode,
+    pub format: Format,
+    /// files and directories, stdin if empty
+    pub inputs: Vec<PathBuf>,
+    pub target: Target,
+    /// more than one input file is processed
+    pub batch: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// `None` if the style should be detected for each input
+    pub style: Option<CommentStyle>,
+    pub detector: Detector,
+    /// replaces the dialect of SQL styles
+    pub sql_dialect: Option<SqlDialect>,
+    pub remove_blanks: bool,
+    pub keep_doc_comments: bool,
+    pub strip_docstrings: bool,
+    pub remove_trailing_commas: bool,
+    pub keep_license_comments: bool,
+    /// comments of these kinds are never stripped
+    pub keep_kinds: Vec<CommentKind>,
+    pub keep_header: bool,
+    pub keep_matching: Vec<Regex>,
+    pub keep_directives: bool,
+    /// patterns for directives besides the built-in ones
+    pub directives: Vec<Regex>,
+    pub stream: bool,
+    /// keep access and modification times of files replaced in place
+    pub preserve_times: bool
 }
 
 impl Config {
-    pub fn from_matches(matches: &ArgMatches) -> io::Result<Self> {
-        let style_arg = (matches.is_present("c-style"),
-                        matches.is_present("xml-style"),
-                        matches.is_present("shell-style"));
-        let comment_style = match style_arg {
-            (true, _, _) => CommentStyle::C,
-            (_, true, _) => CommentStyle::XML,
-            (_, _, true) => CommentStyle::Shell,
-            _ => CommentStyle::Shell
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, AppError> {
+        let style_args = [
+            ("c-style", CommentStyle::C),
+            ("xml-style", CommentStyle::XML),
+            ("html-style", CommentStyle::Html),
+            ("shell-style", CommentStyle::Shell),
+            ("rust-style", CommentStyle::Rust),
+            ("python-style", CommentStyle::Python),
+            ("javascript-style", CommentStyle::JavaScript),
+            ("typescript-style", CommentStyle::TypeScript),
+            ("tsx-style", CommentStyle::Tsx),
+            ("css-style", CommentStyle::Css),
+            ("scss-style", CommentStyle::Scss),
+            ("less-style", CommentStyle::Less),
+            ("sql-style", CommentStyle::Sql(SqlDialect::Standard)),
+            ("ini-style", CommentStyle::Ini),
+            ("toml-style", CommentStyle::Toml),
+            ("properties-style", CommentStyle::Properties),
+            ("yaml-style", CommentStyle::Yaml),
+            ("jsonc-style", CommentStyle::Json)
+        ];
+        let comment_style = match style_args.iter().find(|&&(arg, _)| matches.is_present(arg)) {
+            Some((_, style)) => Some(style.clone()),
+            None => match matches.value_of("style-file") {
+                Some(path) => Some(CommentStyle::Custom(style_file(Path::new(path))?)),
+                None => None
+            }
+        };
+        let mut detector = Detector::new();
+        for mapping in matches.values_of("map").into_iter().flatten() {
+            detector = parse_mapping(detector, mapping)?;
+        }
+        let inputs: Vec<PathBuf> = matches.values_of("INPUT").into_iter().flatten()
+            .map(PathBuf::from)
+            .collect();
+        let batch = inputs.len() > 1 || inputs.iter().any(|p| p.is_dir());
+        let style = match comment_style {
+            Some(style) => Some(style),
+            None if matches.is_present("auto") || batch => None,
+            None => Some(CommentStyle::Shell)
+        };
+        let format = match matches.value_of("format") {
+            Some("json") => Format::Json,
+            Some("ndjson") => Format::Ndjson,
+            _ => Format::Text
+        };
+        let list = matches.is_present("list") || format != Format::Text;
+        let mode = match (list, matches.is_present("check")) {
+            _ if matches.is_present("extract") => Mode::Extract {
+                trim_delimiters: matches.is_present("trim-delimiters"),
+                prefix: matches.is_present("prefix")
+            },
+            (false, false) => Mode::Strip,
+            (list, check) => Mode::Report { list, check }
+        };
+        let target = match (matches.value_of("output-dir"), matches.is_present("in-place")) {
+            (Some(dir), _) => Target::Directory(PathBuf::from(dir)),
+            (None, true) => Target::InPlace(matches.value_of("in-place").map(str::to_owned)),
+            (None, false) if batch && !matches!(mode, Mode::Report { .. }) =>
+                return Err(AppError::Usage("several inputs need --output-dir or --in-place".to_owned())),
+            (None, false) => Target::Single(matches.value_of("output").map(PathBuf::from))
         };
+        if inputs.is_empty() && !matches!(target, Target::Single(_)) {
+            return Err(AppError::Usage("stdin can only be written to stdout or --output".to_owned()));
+        }
         Ok(Config {
-            input: Input::from_arg(matches.value_of("INPUT"))?,
-            output: Output::from_arg(matches.value_of("output"))?,
-            style: comment_style,
-            remove_blanks: !matches.is_present("no-remove-blank-lines")
-        })   
+            mode,
+            format,
+            inputs,
+            target,
+            batch,
+            include: values(matches, "include"),
+            exclude: values(matches, "exclude"),
+            style,
+            detector,
+            sql_dialect: matches.value_of("sql-dialect").and_then(SqlDialect::from_name),
+            remove_blanks: !matches.is_present("no-remove-blank-lines"),
+            keep_doc_comments: matches.is_present("keep-doc-comments"),
+            strip_docstrings: matches.is_present("strip-docstrings"),
+            remove_trailing_commas: matches.is_present("remove-trailing-commas"),
+            keep_license_comments: matches.is_present("keep-license-comments"),
+            keep_kinds: keep_kinds(matches),
+            keep_header: matches.is_present("keep-header"),
+            keep_matching: patterns(matches, "keep-matching")?,
+            keep_directives: !matches.is_present("strip-directives")
+                && !values(matches, "only").iter().any(|kind| kind == "directive"),
+            directives: patterns(matches, "directive")?,
+            stream: matches.is_present("stream"),
+            preserve_times: matches.is_present("preserve-times")
+        })
+    }
+}
+
+fn values(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches.values_of(name).into_iter().flatten()
+        .map(str::to_owned)
+        .collect()
+}
+
+fn patterns(matches: &ArgMatches, name: &str) -> Result<Vec<Regex>, AppError> {
+    values(matches, name).iter()
+        .map(|pattern| Regex::new(pattern)
+            .map_err(|e| AppError::Usage(format!("invalid pattern `{}`: {}", pattern, e))))
+        .collect()
+}
+
+/// Kinds given with `--keep`, or all but the ones given with `--only`
+fn keep_kinds(matches: &ArgMatches) -> Vec<CommentKind> {
+    let kinds = |name| -> Vec<CommentKind> {
+        values(matches, name).iter()
+            .filter_map(|kind| CommentKind::from_name(kind))
+            .collect()
+    };
+    if matches.is_present("only") {
+        let only = kinds("only");
+        CommentKind::ALL.iter().cloned()
+            .filter(|kind| !only.contains(kind))
+            .collect()
+    } else {
+        kinds("keep")
+    }
+}
+
+/// Read a style spec, as YAML if the extension says so and as TOML otherwise
+fn style_file(path: &Path) -> Result<StyleSpec, AppError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path)
+        .map_err(|e| AppError::Io(name.clone(), e))?;
+    let spec = match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml") | Some("yml") => StyleSpec::from_yaml(&text),
+        _ => StyleSpec::from_toml(&text)
+    };
+    spec.map_err(|e| AppError::StyleFile(name, e))
+}
+
+/// Parse an `EXT=STYLE` argument
+fn parse_mapping(detector: Detector, mapping: &str) -> Result<Detector, AppError> {
+    let mut parts = mapping.splitn(2, '=');
+    match (parts.next(), parts.next().and_then(CommentStyle::from_name)) {
+        (Some(pattern), Some(style)) if !pattern.is_empty() =>
+            Ok(detector.map(pattern.trim_start_matches('.'), style)),
+        _ => Err(AppError::Usage(format!("invalid mapping `{}`, expected EXT=STYLE", mapping)))
     }
 }
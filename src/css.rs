@@ -0,0 +1,284 @@
+extern crate regex;
+
+use self::regex::Regex;
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// CSS only has block comments, SCSS and Less also have `//` line comments.
+/// Neither starts in an unquoted `url(//example.org)`, which is why this is
+/// a state machine of its own instead of the one in `c.rs`, it is driven by
+/// the same `Scanner` and `find_comments_impl` though.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseState {
+    Normal,
+    /// how many chars of `url` the identifier matches, more than 3 if it is another one
+    Identifier(usize),
+    FirstSlash,
+    Comment,
+    CommentStar,
+    LineComment,
+    /// after `url(`, where a quoted string or an unquoted url may start
+    UrlStart,
+    Url,
+    UrlEscaped,
+    StringDoubleQuotes,
+    StringDoubleQuotesEscaped,
+    StringSingleQuotes,
+    StringSingleQuotesEscaped,
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Normal
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseAction {
+    Nothing,
+    CommentMightStart,
+    CommentConfirmed(CommentKind),
+    CommentDismissed,
+    /// comment ends after the current char
+    CommentEnds,
+    /// comment ends before the current char
+    LineCommentEnds,
+    CommentUnterminated
+}
+
+const URL: &[u8] = b"url";
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    let state = match c {
+        '/'     => return (ParseState::FirstSlash, ParseAction::CommentMightStart),
+        '"'     => ParseState::StringDoubleQuotes,
+        '\''    => ParseState::StringSingleQuotes,
+        c if is_identifier(c)
+                => ParseState::Identifier(identifier(0, c)),
+        _       => ParseState::Normal
+    };
+    (state, ParseAction::Nothing)
+}
+
+fn identifier(matched: usize, c: char) -> usize {
+    match URL.get(matched) {
+        Some(&b) if c.to_ascii_lowercase() == b as char => matched + 1,
+        _ => URL.len() + 1
+    }
+}
+
+/// `line_comments` enables the `//` comments of SCSS and Less
+pub fn state_transition(line_comments: bool, from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::Normal =>
+                normal(c),
+            ParseState::Identifier(matched) => match c {
+                '(' if matched == URL.len()
+                        => (ParseState::UrlStart, ParseAction::Nothing),
+                c if is_identifier(c)
+                        => (ParseState::Identifier(identifier(matched, c)), ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::FirstSlash => match c {
+                '*'     => (ParseState::Comment, ParseAction::CommentConfirmed(CommentKind::Block)),
+                '/' if line_comments
+                        => (ParseState::LineComment, ParseAction::CommentConfirmed(CommentKind::Line)),
+                '/'     => (ParseState::FirstSlash, ParseAction::CommentMightStart),
+                _       => (normal(c).0, ParseAction::CommentDismissed)
+            },
+            ParseState::Comment => match c {
+                '*'     => (ParseState::CommentStar, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::CommentStar => match c {
+                '/'     => (ParseState::Normal, ParseAction::CommentEnds),
+                '*'     => (ParseState::CommentStar, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::LineComment => match c {
+                '\n'    => (ParseState::Normal, ParseAction::LineCommentEnds),
+                _       => (ParseState::LineComment, ParseAction::Nothing)
+            },
+            ParseState::UrlStart => match c {
+                '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
+                '\''    => (ParseState::StringSingleQuotes, ParseAction::Nothing),
+                ')'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::UrlEscaped, ParseAction::Nothing),
+                c if c.is_whitespace()
+                        => (ParseState::UrlStart, ParseAction::Nothing),
+                _       => (ParseState::Url, ParseAction::Nothing)
+            },
+            ParseState::Url => match c {
+                ')'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::UrlEscaped, ParseAction::Nothing),
+                _       => (ParseState::Url, ParseAction::Nothing)
+            },
+            ParseState::UrlEscaped =>
+                (ParseState::Url, ParseAction::Nothing),
+            ParseState::StringDoubleQuotes => match c {
+                '"'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::StringDoubleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::StringDoubleQuotes, ParseAction::Nothing)
+            },
+            ParseState::StringDoubleQuotesEscaped =>
+                (ParseState::StringDoubleQuotes, ParseAction::Nothing),
+            ParseState::StringSingleQuotes => match c {
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::StringSingleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::StringSingleQuotes, ParseAction::Nothing)
+            },
+            ParseState::StringSingleQuotesEscaped =>
+                (ParseState::StringSingleQuotes, ParseAction::Nothing),
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::FirstSlash => (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::Comment | ParseState::CommentStar => (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::LineComment => (ParseState::End, ParseAction::LineCommentEnds),
+            _ => (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum CommentState {
+    NotInComment,
+    MaybeInComment(usize),
+    InComment(usize, CommentKind)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from, _) => Some(from)
+        }
+    }
+}
+
+pub fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentMightStart => {
+            comment_state = CommentState::MaybeInComment(position);
+        },
+        ParseAction::CommentConfirmed(kind) => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    comment_state = CommentState::InComment(from, kind);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("css style"), position));
+                }
+            }
+        },
+        ParseAction::CommentDismissed => {
+            comment_state = CommentState::NotInComment;
+        },
+        ParseAction::CommentEnds | ParseAction::LineCommentEnds => {
+            let to = if action == ParseAction::CommentEnds { position + 1 } else { position };
+            match comment_state {
+                CommentState::InComment(from, kind) => {
+                    matches.push(CommentMatch { from, to, kind });
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("css style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("css style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+/// Loud comments like `/*! ... */` that minifiers and Sass keep
+pub fn loud_comments() -> Regex {
+    Regex::new(r"^/\*!").expect("loud comment pattern is a valid regex")
+}
+
+pub fn find_comments(input: &str, line_comments: bool) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, move |s, c| state_transition(line_comments, s, c), do_action)
+}
+
+pub fn scanner(line_comments: bool) -> Box<dyn Scan> {
+    Box::new(Scanner::new(move |s, c| state_transition(line_comments, s, c), do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::comment_texts;
+
+    #[test]
+    fn block_comments() {
+        let input = "a { color: red; /* one */ }\n/* two\n */b::after { content: '/* no */'; }";
+        let expected = Ok(vec![
+            CommentMatch { from: 16, to: 25, kind: CommentKind::Block },
+            CommentMatch { from: 28, to: 38, kind: CommentKind::Block }
+        ]);
+        assert_eq!(expected, find_comments(input, false));
+    }
+
+    #[test]
+    fn no_line_comments_in_css() {
+        let input = "a { b: c // d; }";
+        assert_eq!(Vec::<(&str, CommentKind)>::new(), comment_texts(input, find_comments(input, false)));
+        assert_eq!(vec![("// d; }", CommentKind::Line)], comment_texts(input, find_comments(input, true)));
+    }
+
+    #[test]
+    fn unquoted_url() {
+        let input = "a { background: url(http://x/*y*/z.png) URL( //x\\)) } // c\nb { c: myurl(//d) }";
+        let expected = vec![
+            ("// c", CommentKind::Line),
+            ("//d) }", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, true)));
+        let input = "@import url( \"//x\" ) /* c */;";
+        assert_eq!(vec![("/* c */", CommentKind::Block)], comment_texts(input, find_comments(input, true)));
+    }
+
+    #[test]
+    fn loud_comment_pattern() {
+        assert!(loud_comments().is_match("/*! v1.0 */"));
+        assert!(!loud_comments().is_match("/* ! */"));
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let input = "a {}\n/* b";
+        let expected = Err(ScanError::new(ErrorKind::UnterminatedComment, 5).locate(input));
+        assert_eq!(expected, find_comments(input, true));
+    }
+}
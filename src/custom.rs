@@ -0,0 +1,564 @@
+extern crate serde_yaml;
+extern crate toml;
+
+use std::rc::Rc;
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError,
+            SpecError, find_comments_impl};
+
+/// Declarative description of a comment style, read from TOML or YAML like
+///
+/// ```toml
+/// name = "lua"
+/// line = ["--"]
+/// block = [["--[[", "]]"]]
+/// strings = ["\"", "'"]
+/// escape = "\\"
+/// raw_strings = [["[[", "]]"]]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleSpec {
+    pub name: String,
+    /// prefixes of comments running until the end of the line
+    #[serde(default)]
+    pub line: Vec<String>,
+    /// opening and closing delimiters of block comments
+    #[serde(default)]
+    pub block: Vec<(String, String)>,
+    /// whether block comments nest like in Rust
+    #[serde(default)]
+    pub nested: bool,
+    /// string delimiters, each string ends with the delimiter it started with
+    #[serde(default)]
+    pub strings: Vec<String>,
+    /// char that escapes the next char in strings
+    #[serde(default)]
+    pub escape: Option<char>,
+    /// opening and closing delimiters of strings without escapes
+    #[serde(default)]
+    pub raw_strings: Vec<(String, String)>
+}
+
+/// Languages that look like C, but whose strings or comments the C style would get wrong
+const BUILTIN: &[&str] = &[
+    /* raw strings in backticks */
+    r#"
+        name = "go"
+        line = ["//"]
+        block = [["/*", "*/"]]
+        strings = ['"', "'"]
+        escape = '\'
+        raw_strings = [["`", "`"]]
+    "#,
+    /* nested block comments */
+    r#"
+        name = "kotlin"
+        line = ["//"]
+        block = [["/*", "*/"]]
+        nested = true
+        strings = ['"', "'"]
+        escape = '\'
+        raw_strings = [['"""', '"""']]
+    "#,
+    r##"
+        name = "swift"
+        line = ["//"]
+        block = [["/*", "*/"]]
+        nested = true
+        strings = ['"', '"""']
+        escape = '\'
+        raw_strings = [['#"', '"#']]
+    "##
+];
+
+impl StyleSpec {
+    /// Built-in spec with the given name, `go`, `kotlin` or `swift`
+    pub fn builtin(name: &str) -> Option<StyleSpec> {
+        BUILTIN.iter()
+            .map(|text| StyleSpec::from_toml(text).expect("built-in spec is valid"))
+            .find(|spec| spec.name == name)
+    }
+
+    pub fn from_toml(text: &str) -> Result<StyleSpec, SpecError> {
+        let spec: StyleSpec = toml::from_str(text)
+            .map_err(|e| SpecError::Parse(e.to_string()))?;
+        spec.validate()
+    }
+
+    pub fn from_yaml(text: &str) -> Result<StyleSpec, SpecError> {
+        let spec: StyleSpec = serde_yaml::from_str(text)
+            .map_err(|e| SpecError::Parse(e.to_string()))?;
+        spec.validate()
+    }
+
+    fn validate(self) -> Result<StyleSpec, SpecError> {
+        if self.line.is_empty() && self.block.is_empty() {
+            return Err(SpecError::Invalid("neither line nor block comments are defined".to_owned()));
+        }
+        let pairs = self.block.iter().chain(self.raw_strings.iter());
+        let mut delimiters = self.line.iter().chain(self.strings.iter())
+            .chain(pairs.flat_map(|(open, close)| vec![open, close]));
+        if delimiters.any(|d| d.is_empty()) {
+            return Err(SpecError::Invalid("delimiters must not be empty".to_owned()));
+        }
+        Ok(self)
+    }
+
+    /// Opening and closing delimiters of the comments, longest first
+    pub fn delimiters(&self) -> Vec<(&str, &str)> {
+        let mut delimiters: Vec<(&str, &str)> = self.line.iter()
+            .map(|open| (open.as_str(), ""))
+            .chain(self.block.iter().map(|(open, close)| (open.as_str(), close.as_str())))
+            .collect();
+        delimiters.sort_by_key(|&(open, _)| usize::MAX - open.len());
+        delimiters
+    }
+}
+
+/// What reading a whole token does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Effect {
+    LineComment,
+    BlockComment(usize),
+    Nest,
+    Close,
+    String(usize),
+    RawString(usize)
+}
+
+#[derive(Debug)]
+struct Token {
+    text: Vec<char>,
+    effect: Effect
+}
+
+impl Token {
+    fn new(text: &str, effect: Effect) -> Self {
+        Token { text: text.chars().collect(), effect }
+    }
+}
+
+/// The tokens that can be read in each state
+#[derive(Debug)]
+struct Tables {
+    code: Vec<Token>,
+    blocks: Vec<Vec<Token>>,
+    strings: Vec<Vec<Token>>,
+    raw_strings: Vec<Vec<Token>>,
+    escape: Option<char>
+}
+
+impl Tables {
+    fn new(spec: &StyleSpec) -> Self {
+        let code = spec.line.iter().map(|open| Token::new(open, Effect::LineComment))
+            .chain(spec.block.iter().enumerate().map(|(i, d)| Token::new(&d.0, Effect::BlockComment(i))))
+            .chain(spec.strings.iter().enumerate().map(|(i, d)| Token::new(d, Effect::String(i))))
+            .chain(spec.raw_strings.iter().enumerate().map(|(i, d)| Token::new(&d.0, Effect::RawString(i))))
+            .collect();
+        let blocks = spec.block.iter()
+            .map(|(open, close)| {
+                let mut tokens = vec![Token::new(close, Effect::Close)];
+                if spec.nested {
+                    tokens.push(Token::new(open, Effect::Nest));
+                }
+                tokens
+            })
+            .collect();
+        Tables {
+            code,
+            blocks,
+            strings: spec.strings.iter().map(|d| vec![Token::new(d, Effect::Close)]).collect(),
+            raw_strings: spec.raw_strings.iter().map(|d| vec![Token::new(&d.1, Effect::Close)]).collect(),
+            escape: spec.escape
+        }
+    }
+
+    fn tokens(&self, state: ParseState) -> &[Token] {
+        match state {
+            ParseState::Code(_) => &self.code,
+            ParseState::BlockComment { block, .. } => &self.blocks[block],
+            ParseState::String { string, .. } => &self.strings[string],
+            ParseState::RawString { raw, .. } => &self.raw_strings[raw],
+            ParseState::LineComment | ParseState::End => &[]
+        }
+    }
+}
+
+/// Token and number of its chars read so far
+type Prefix = Option<(usize, usize)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Code(Prefix),
+    LineComment,
+    BlockComment { block: usize, depth: usize, prefix: Prefix },
+    String { string: usize, escaped: bool, prefix: Prefix },
+    RawString { raw: usize, prefix: Prefix },
+    End
+}
+
+impl ParseState {
+    fn prefix(&self) -> Prefix {
+        match *self {
+            ParseState::Code(prefix)
+            | ParseState::BlockComment { prefix, .. }
+            | ParseState::String { prefix, .. }
+            | ParseState::RawString { prefix, .. } => prefix,
+            ParseState::LineComment | ParseState::End => None
+        }
+    }
+
+    fn with_prefix(self, prefix: Prefix) -> Self {
+        match self {
+            ParseState::Code(_) => ParseState::Code(prefix),
+            ParseState::BlockComment { block, depth, .. } => ParseState::BlockComment { block, depth, prefix },
+            ParseState::String { string, escaped, .. } => ParseState::String { string, escaped, prefix },
+            ParseState::RawString { raw, .. } => ParseState::RawString { raw, prefix },
+            state => state
+        }
+    }
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Code(None)
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+/// Offsets are in bytes relative to the current char, which may be preceded by
+/// the chars of a token that only turned out to be complete now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+enum Event {
+    CommentStarts(isize, CommentKind),
+    CommentEnds(isize),
+    CommentUnterminated
+}
+
+/// Reading one char may complete a token that was held back, and reading its chars again
+/// may cause any number of events
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseAction {
+    events: Vec<Event>,
+    /// start of a token in the code that might begin a comment
+    hold: Option<isize>
+}
+
+impl ParseAction {
+    fn new() -> Self {
+        ParseAction { events: Vec::new(), hold: None }
+    }
+
+    fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+}
+
+fn len(chars: &[char]) -> isize {
+    chars.iter().map(|c| c.len_utf8() as isize).sum()
+}
+
+/// Apply a complete token that started `start` and ended `end` bytes relative to the current char
+fn apply(state: ParseState, effect: Effect, start: isize, end: isize, action: &mut ParseAction) -> ParseState {
+    let state = state.with_prefix(None);
+    match (state, effect) {
+        (ParseState::Code(_), Effect::LineComment) => {
+            action.push(Event::CommentStarts(start, CommentKind::Line));
+            ParseState::LineComment
+        }
+        (ParseState::Code(_), Effect::BlockComment(block)) => {
+            action.push(Event::CommentStarts(start, CommentKind::Block));
+            ParseState::BlockComment { block, depth: 1, prefix: None }
+        }
+        (ParseState::Code(_), Effect::String(string)) =>
+            ParseState::String { string, escaped: false, prefix: None },
+        (ParseState::Code(_), Effect::RawString(raw)) =>
+            ParseState::RawString { raw, prefix: None },
+        (ParseState::BlockComment { block, depth, .. }, Effect::Nest) =>
+            ParseState::BlockComment { block, depth: depth + 1, prefix: None },
+        (ParseState::BlockComment { depth: 1, .. }, Effect::Close) => {
+            action.push(Event::CommentEnds(end));
+            ParseState::Code(None)
+        }
+        (ParseState::BlockComment { block, depth, .. }, Effect::Close) =>
+            ParseState::BlockComment { block, depth: depth - 1, prefix: None },
+        (ParseState::String { .. }, Effect::Close) | (ParseState::RawString { .. }, Effect::Close) =>
+            ParseState::Code(None),
+        (state, _) => state
+    }
+}
+
+/// Read a char that is not part of a token
+fn plain(tables: &Tables, state: ParseState, c: char, offset: isize, action: &mut ParseAction) -> ParseState {
+    match state {
+        ParseState::LineComment if c == '\n' => {
+            action.push(Event::CommentEnds(offset));
+            ParseState::Code(None)
+        }
+        ParseState::String { string, escaped: false, prefix: None } if Some(c) == tables.escape =>
+            ParseState::String { string, escaped: true, prefix: None },
+        ParseState::String { string, escaped: true, prefix: None } =>
+            ParseState::String { string, escaped: false, prefix: None },
+        state => state
+    }
+}
+
+/// Read `c`, which is `offset` bytes before the current char
+fn step(tables: &Tables, state: ParseState, c: char, offset: isize, action: &mut ParseAction) -> ParseState {
+    if let ParseState::String { escaped: true, .. } = state {
+        return plain(tables, state, c, offset, action);
+    }
+    if let ParseState::String { prefix: None, .. } = state {
+        if Some(c) == tables.escape {
+            return plain(tables, state, c, offset, action);
+        }
+    }
+    let tokens = tables.tokens(state);
+    let read: &[char] = match state.prefix() {
+        Some((token, n)) => &tokens[token].text[..n],
+        None => &[]
+    };
+    let n = read.len();
+    let extends = |t: &Token| t.text.len() > n && t.text[..n] == *read && t.text[n] == c;
+    if let Some(token) = tokens.iter().position(extends) {
+        let text = &tokens[token].text[..n + 1];
+        let longer = tokens.iter().any(|t| t.text.len() > n + 1 && t.text[..n + 1] == *text);
+        if !longer {
+            let start = offset - len(&text[..n]);
+            return apply(state, tokens[token].effect, start, offset + c.len_utf8() as isize, action);
+        }
+        return state.with_prefix(Some((token, n + 1)));
+    }
+    let state = resolve(tables, state, offset - len(read), action);
+    match state.prefix() {
+        /* the resolved prefix left a new one, which `c` might extend */
+        Some(_) => step(tables, state, c, offset, action),
+        None if read.is_empty() => plain(tables, state, c, offset, action),
+        None => step(tables, state, c, offset, action)
+    }
+}
+
+/// The chars read for the prefix of `state` do not continue its token, apply the longest token they
+/// start with, or read the first as a plain char, and read the rest again. `start` is the offset of
+/// the first of them.
+fn resolve(tables: &Tables, state: ParseState, start: isize, action: &mut ParseAction) -> ParseState {
+    let (token, n) = match state.prefix() {
+        Some(prefix) => prefix,
+        None => return state
+    };
+    let tokens = tables.tokens(state);
+    let read: Vec<char> = tokens[token].text[..n].to_vec();
+    let complete = tokens.iter()
+        .filter(|t| t.text.len() <= n && t.text[..] == read[..t.text.len()])
+        .max_by_key(|t| t.text.len());
+    let (mut state, used) = match complete {
+        Some(t) => {
+            let used = t.text.len();
+            (apply(state, t.effect, start, start + len(&read[..used]), action), used)
+        }
+        None => (plain(tables, state.with_prefix(None), read[0], start, action), 1)
+    };
+    let mut offset = start + len(&read[..used]);
+    for &c in &read[used..] {
+        state = step(tables, state, c, offset, action);
+        offset += c.len_utf8() as isize;
+    }
+    state
+}
+
+fn state_transition(tables: &Tables, from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    let mut action = ParseAction::new();
+    let state = match current_char {
+        Some(c) => step(tables, from, c, 0, &mut action),
+        None => {
+            let mut state = from;
+            /* the chars of a prefix are the last ones of the input */
+            while let Some((token, n)) = state.prefix() {
+                let start = -len(&tables.tokens(state)[token].text[..n]);
+                state = resolve(tables, state, start, &mut action);
+            }
+            match state {
+                ParseState::LineComment => action.push(Event::CommentEnds(0)),
+                ParseState::BlockComment { .. } => action.push(Event::CommentUnterminated),
+                _ => ()
+            }
+            ParseState::End
+        }
+    };
+    if let ParseState::Code(Some((token, n))) = state {
+        let c_len = current_char.map_or(0, |c| c.len_utf8() as isize);
+        action.hold = Some(c_len - len(&tables.code[token].text[..n]));
+    }
+    (state, action)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CommentState {
+    comment: Option<(usize, CommentKind)>,
+    hold: Option<usize>
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState { comment: None, hold: None }
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        self.comment.map(|(from, _)| from).or(self.hold)
+    }
+}
+
+fn at(position: usize, offset: isize) -> usize {
+    (position as isize + offset) as usize
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    for event in action.events {
+        match (event, comment_state.comment) {
+            (Event::CommentStarts(offset, kind), None) => {
+                comment_state.comment = Some((at(position, offset), kind));
+            },
+            (Event::CommentEnds(offset), Some((from, kind))) => {
+                matches.push(CommentMatch { from, to: at(position, offset), kind });
+                comment_state.comment = None;
+            },
+            (Event::CommentUnterminated, Some((from, _))) => {
+                return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+            },
+            _ => {
+                return Err(ScanError::new(ErrorKind::Parser("custom style"), position));
+            }
+        }
+    }
+    comment_state.hold = action.hold.map(|offset| at(position, offset));
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str, spec: &StyleSpec) -> Result<Vec<CommentMatch>, Error> {
+    let tables = Tables::new(spec);
+    find_comments_impl(input, |state, c| state_transition(&tables, state, c), do_action)
+}
+
+pub fn scanner(spec: &StyleSpec) -> Box<dyn Scan> {
+    let tables = Rc::new(Tables::new(spec));
+    Box::new(Scanner::new(move |state, c| state_transition(&tables, state, c), do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::comment_texts;
+
+    fn lua() -> StyleSpec {
+        StyleSpec::from_toml(r#"
+            name = "lua"
+            line = ["--"]
+            block = [["--[[", "]]"]]
+            strings = ['"', "'"]
+            escape = '\'
+            raw_strings = [["[[", "]]"]]
+        "#).unwrap()
+    }
+
+    #[test]
+    fn line_and_block_comments() {
+        let input = "a = 1 -- one\n--[[ two\n]] b = 2 --[ three\n--";
+        let expected = vec![
+            ("-- one", CommentKind::Line),
+            ("--[[ two\n]]", CommentKind::Block),
+            ("--[ three", CommentKind::Line),
+            ("--", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &lua())));
+    }
+
+    #[test]
+    fn builtin_specs() {
+        let go = StyleSpec::builtin("go").unwrap();
+        let input = "var s = `a // b\\` // c\nvar r = '\"' /* d */";
+        let expected = vec![
+            ("// c", CommentKind::Line),
+            ("/* d */", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &go)));
+        let kotlin = StyleSpec::builtin("kotlin").unwrap();
+        let input = "/* a /* b */ c */ val s = \"\"\"x \" // y\"\"\" // d";
+        let expected = vec![
+            ("/* a /* b */ c */", CommentKind::Block),
+            ("// d", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &kotlin)));
+        let swift = StyleSpec::builtin("swift").unwrap();
+        let input = "/* a /* b */ c */ let s = #\"\\\" // \"# + \"\"\"\n\\\"\"\" // \"\"\" // d";
+        let expected = vec![
+            ("/* a /* b */ c */", CommentKind::Block),
+            ("// d", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &swift)));
+        assert_eq!(None, StyleSpec::builtin("cobol"));
+    }
+
+    #[test]
+    fn strings_and_raw_strings() {
+        let input = "s = \"-- \\\" --\" .. '--' .. [[ -- \\]] -- c\n";
+        assert_eq!(vec![("-- c", CommentKind::Line)], comment_texts(input, find_comments(input, &lua())));
+    }
+
+    #[test]
+    fn prefix_of_longer_tokens() {
+        let spec = StyleSpec::from_yaml("
+            name: py
+            line: ['#']
+            strings: ['\"\"\"', '\"', \"'\"]
+            escape: '\\'
+        ").unwrap();
+        let input = "a = \"\" # x\nb = \"\"\" # \" \"\"\" # y\n";
+        let expected = vec![
+            ("# x", CommentKind::Line),
+            ("# y", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &spec)));
+    }
+
+    #[test]
+    fn many_events_for_one_char() {
+        /* the `x` shows that `<><><><>` is not the string, which reads four comments again */
+        let spec = StyleSpec::from_toml("name = 'x'\nblock = [['<', '>']]\nstrings = ['<><><><>!']").unwrap();
+        let input = "<><><><>x";
+        let expected = vec![
+            ("<>", CommentKind::Block),
+            ("<>", CommentKind::Block),
+            ("<>", CommentKind::Block),
+            ("<>", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, &spec)));
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let spec = StyleSpec::from_toml("name = 'ml'\nblock = [['(*', '*)']]\nnested = true").unwrap();
+        let input = "x (* a (* b *) c *) y";
+        assert_eq!(vec![("(* a (* b *) c *)", CommentKind::Block)], comment_texts(input, find_comments(input, &spec)));
+        let error = find_comments("x (* a (* b *)", &spec).unwrap_err();
+        assert_eq!(ErrorKind::UnterminatedComment, error.kind);
+        assert_eq!(2, error.location.offset);
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert!(StyleSpec::from_toml("name = 'x'").is_err());
+        assert!(StyleSpec::from_toml("name = 'x'\nline = ['']").is_err());
+        assert!(StyleSpec::from_toml("name = 'x'\nline = ['#']\nunknown = 1").is_err());
+        assert!(StyleSpec::from_yaml("name: [").is_err());
+    }
+}
@@ -0,0 +1,352 @@
+use std::path::Path;
+use super::{CommentStyle, SqlDialect, StyleSpec};
+
+/// How many lines at the start of a file are searched for a modeline
+const MODELINE_LINES: usize = 5;
+
+const EXTENSIONS: &[(&str, CommentStyle)] = &[
+    ("c", CommentStyle::C),
+    ("h", CommentStyle::C),
+    ("cc", CommentStyle::C),
+    ("cpp", CommentStyle::C),
+    ("cxx", CommentStyle::C),
+    ("hpp", CommentStyle::C),
+    ("hh", CommentStyle::C),
+    ("cs", CommentStyle::C),
+    ("java", CommentStyle::C),
+    ("js", CommentStyle::JavaScript),
+    ("mjs", CommentStyle::JavaScript),
+    ("cjs", CommentStyle::JavaScript),
+    ("jsx", CommentStyle::JavaScript),
+    ("tsx", CommentStyle::Tsx),
+    ("ts", CommentStyle::TypeScript),
+    ("mts", CommentStyle::TypeScript),
+    ("cts", CommentStyle::TypeScript),
+    ("json", CommentStyle::Json),
+    ("jsonc", CommentStyle::Json),
+    ("rs", CommentStyle::Rust),
+    ("py", CommentStyle::Python),
+    ("pyw", CommentStyle::Python),
+    ("pyi", CommentStyle::Python),
+    ("xml", CommentStyle::XML),
+    ("xsd", CommentStyle::XML),
+    ("xsl", CommentStyle::XML),
+    ("xslt", CommentStyle::XML),
+    ("svg", CommentStyle::XML),
+    ("html", CommentStyle::Html),
+    ("htm", CommentStyle::Html),
+    ("css", CommentStyle::Css),
+    ("scss", CommentStyle::Scss),
+    ("less", CommentStyle::Less),
+    ("xhtml", CommentStyle::XML),
+    ("plist", CommentStyle::XML),
+    ("sh", CommentStyle::Shell),
+    ("bash", CommentStyle::Shell),
+    ("zsh", CommentStyle::Shell),
+    ("ksh", CommentStyle::Shell),
+    ("pl", CommentStyle::Shell),
+    ("rb", CommentStyle::Shell),
+    ("r", CommentStyle::Shell),
+    ("mk", CommentStyle::Shell),
+    ("cmake", CommentStyle::Shell),
+    ("toml", CommentStyle::Toml),
+    ("ini", CommentStyle::Ini),
+    ("properties", CommentStyle::Properties),
+    ("yaml", CommentStyle::Yaml),
+    ("yml", CommentStyle::Yaml),
+    ("conf", CommentStyle::Shell),
+    ("cfg", CommentStyle::Ini),
+    ("sql", CommentStyle::Sql(SqlDialect::Standard)),
+    ("ddl", CommentStyle::Sql(SqlDialect::Standard)),
+    ("pgsql", CommentStyle::Sql(SqlDialect::PostgreSql)),
+    ("psql", CommentStyle::Sql(SqlDialect::PostgreSql)),
+    ("mysql", CommentStyle::Sql(SqlDialect::MySql))
+];
+
+const FILE_NAMES: &[(&str, CommentStyle)] = &[
+    ("makefile", CommentStyle::Shell),
+    ("gnumakefile", CommentStyle::Shell),
+    ("dockerfile", CommentStyle::Shell),
+    ("cmakelists.txt", CommentStyle::Shell),
+    ("gemfile", CommentStyle::Shell),
+    ("rakefile", CommentStyle::Shell),
+    (".bashrc", CommentStyle::Shell),
+    (".bash_profile", CommentStyle::Shell),
+    (".profile", CommentStyle::Shell),
+    (".zshrc", CommentStyle::Shell),
+    (".gitignore", CommentStyle::Shell),
+    (".gitattributes", CommentStyle::Shell),
+    (".editorconfig", CommentStyle::Ini),
+    (".gitconfig", CommentStyle::Ini),
+    (".gitmodules", CommentStyle::Ini),
+    ("cargo.lock", CommentStyle::Toml),
+    ("pipfile", CommentStyle::Toml)
+];
+
+/// Interpreter, vim filetype and emacs mode names
+const LANGUAGES: &[(&str, CommentStyle)] = &[
+    ("c", CommentStyle::C),
+    ("cpp", CommentStyle::C),
+    ("c++", CommentStyle::C),
+    ("java", CommentStyle::C),
+    ("javascript", CommentStyle::JavaScript),
+    ("javascriptreact", CommentStyle::JavaScript),
+    ("js", CommentStyle::JavaScript),
+    ("node", CommentStyle::JavaScript),
+    ("typescript", CommentStyle::TypeScript),
+    ("typescriptreact", CommentStyle::Tsx),
+    ("ts-node", CommentStyle::TypeScript),
+    ("rust", CommentStyle::Rust),
+    ("python", CommentStyle::Python),
+    ("xml", CommentStyle::XML),
+    ("nxml", CommentStyle::XML),
+    ("html", CommentStyle::Html),
+    ("svg", CommentStyle::XML),
+    ("css", CommentStyle::Css),
+    ("scss", CommentStyle::Scss),
+    ("less", CommentStyle::Less),
+    ("sh", CommentStyle::Shell),
+    ("bash", CommentStyle::Shell),
+    ("zsh", CommentStyle::Shell),
+    ("dash", CommentStyle::Shell),
+    ("ksh", CommentStyle::Shell),
+    ("fish", CommentStyle::Shell),
+    ("shell-script", CommentStyle::Shell),
+    ("perl", CommentStyle::Shell),
+    ("ruby", CommentStyle::Shell),
+    ("make", CommentStyle::Shell),
+    ("makefile", CommentStyle::Shell),
+    ("conf", CommentStyle::Shell),
+    ("toml", CommentStyle::Toml),
+    ("ini", CommentStyle::Ini),
+    ("properties", CommentStyle::Properties),
+    ("yaml", CommentStyle::Yaml),
+    ("json", CommentStyle::Json),
+    ("jsonc", CommentStyle::Json),
+    ("sql", CommentStyle::Sql(SqlDialect::Standard)),
+    ("pgsql", CommentStyle::Sql(SqlDialect::PostgreSql)),
+    ("mysql", CommentStyle::Sql(SqlDialect::MySql)),
+    ("sqlite", CommentStyle::Sql(SqlDialect::Sqlite))
+];
+
+/// Extensions of the languages with a built-in `StyleSpec`
+const SPEC_EXTENSIONS: &[(&str, &str)] = &[
+    ("go", "go"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("swift", "swift")
+];
+
+fn lookup(table: &[(&str, CommentStyle)], name: &str) -> Option<CommentStyle> {
+    let name = name.to_lowercase();
+    table.iter()
+        .find(|&&(n, _)| n == name)
+        .map(|(_, style)| style.clone())
+}
+
+/// Language of an interpreter like `python3.11` or `bash`
+fn language(name: &str) -> Option<CommentStyle> {
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    lookup(LANGUAGES, name).or_else(|| builtin_spec(&name.to_lowercase()))
+}
+
+fn builtin_spec(name: &str) -> Option<CommentStyle> {
+    StyleSpec::builtin(name).map(CommentStyle::Custom)
+}
+
+fn extension(extension: &str) -> Option<CommentStyle> {
+    let extension = extension.to_lowercase();
+    lookup(EXTENSIONS, &extension).or_else(|| {
+        SPEC_EXTENSIONS.iter()
+            .find(|&&(e, _)| e == extension)
+            .and_then(|&(_, name)| builtin_spec(name))
+    })
+}
+
+fn shebang(first_line: &str) -> Option<CommentStyle> {
+    if !first_line.starts_with("#!") {
+        return None;
+    }
+    let mut words = first_line[2..].split_whitespace();
+    let program = words.next()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    if program == "env" {
+        /* skip options like `env -S` */
+        let program = words.find(|w| !w.starts_with('-'))?;
+        language(program)
+    } else {
+        language(program)
+    }
+}
+
+/// Offset after the first `vim:`, `vi:` or `ex:` that starts the line or follows whitespace,
+/// so `http://vim:8080` is not a modeline
+fn vim_modeline(line: &str) -> Option<usize> {
+    ["vim:", "vi:", "ex:"].iter()
+        .flat_map(|m| line.match_indices(m))
+        .filter(|&(i, _)| line[..i].chars().next_back().is_none_or(char::is_whitespace))
+        .map(|(i, m)| i + m.len())
+        .min()
+}
+
+/* vim: `vim: set ft=python:` or `vi: filetype=sh`
+   emacs: `-*- mode: python -*-` or `-*- python -*-` */
+fn modeline(line: &str) -> Option<CommentStyle> {
+    if let Some(start) = line.find("-*-") {
+        let rest = &line[start + 3..];
+        let inner = &rest[..rest.find("-*-")?];
+        let mode = match inner.find("mode:") {
+            Some(i) => inner[i + 5..].split(';').next().unwrap_or(""),
+            None if !inner.contains(':') => inner,
+            None => return None
+        };
+        return language(mode.trim().trim_end_matches("-mode"));
+    }
+    let start = vim_modeline(line)?;
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .filter_map(|option| {
+            let mut kv = option.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("ft"), Some(v)) | (Some("filetype"), Some(v)) | (Some("syntax"), Some(v)) =>
+                    language(v),
+                _ => None
+            }
+        })
+        .next()
+}
+
+/// Selects the comment style of a file, user mappings take precedence over
+/// the built-in extensions, then the shebang and modelines are inspected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Detector {
+    mappings: Vec<(String, CommentStyle)>
+}
+
+impl Detector {
+    pub fn new() -> Self {
+        Detector::default()
+    }
+
+    /// Use `style` for files with the given extension (without dot) or file name
+    pub fn map<S: Into<String>>(mut self, pattern: S, style: CommentStyle) -> Self {
+        self.mappings.insert(0, (pattern.into().to_lowercase(), style));
+        self
+    }
+
+    pub fn detect(&self, path: Option<&Path>, first_bytes: &[u8]) -> Option<CommentStyle> {
+        self.detect_path(path).or_else(|| detect_content(first_bytes))
+    }
+
+    fn detect_path(&self, path: Option<&Path>) -> Option<CommentStyle> {
+        let path = path?;
+        let file_name = path.file_name()?.to_str()?.to_lowercase();
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let is_match = |pattern: &str| {
+            pattern == file_name || Some(pattern) == extension.as_deref()
+        };
+        self.mappings.iter()
+            .find(|&(pattern, _)| is_match(pattern))
+            .map(|(_, style)| style.clone())
+            .or_else(|| lookup(FILE_NAMES, &file_name))
+            .or_else(|| extension.as_deref().and_then(self::extension))
+    }
+}
+
+fn detect_content(first_bytes: &[u8]) -> Option<CommentStyle> {
+    /* the first bytes might end inside a char */
+    let text = String::from_utf8_lossy(first_bytes);
+    let mut lines = text.lines();
+    let first_line = lines.next()?;
+    shebang(first_line).or_else(|| {
+        Some(first_line).into_iter()
+            .chain(lines)
+            .take(MODELINE_LINES)
+            .filter_map(modeline)
+            .next()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use super::super::CommentStyle;
+
+    fn detect(path: &str, first_bytes: &str) -> Option<CommentStyle> {
+        let path = if path.is_empty() { None } else { Some(Path::new(path)) };
+        Detector::new().detect(path, first_bytes.as_bytes())
+    }
+
+    #[test]
+    fn by_extension() {
+        assert_eq!(Some(CommentStyle::C), detect("src/main.C", ""));
+        assert_eq!(Some(CommentStyle::Rust), detect("lib.rs", ""));
+        assert_eq!(Some(CommentStyle::Html), detect("a/b/index.html", ""));
+        assert_eq!(Some(CommentStyle::XML), detect("a/b/index.xhtml", ""));
+        assert_eq!(Some(CommentStyle::Toml), detect("Cargo.toml", ""));
+        assert_eq!(Some(CommentStyle::Ini), detect("setup.cfg", ""));
+        assert_eq!(Some(CommentStyle::Yaml), detect("deploy/app.yml", ""));
+        assert_eq!(Some(CommentStyle::Json), detect(".vscode/settings.json", ""));
+        assert_eq!(Some(CommentStyle::Tsx), detect("src/App.tsx", ""));
+        assert_eq!(Some(CommentStyle::TypeScript), detect("src/index.ts", ""));
+        assert_eq!(Some(CommentStyle::Scss), detect("styles/_mixins.scss", ""));
+        assert_eq!(Some(CommentStyle::Properties), detect("messages_de.properties", ""));
+        assert_eq!(Some(CommentStyle::Shell), detect("Makefile", ""));
+        assert_eq!(Some(CommentStyle::Sql(SqlDialect::PostgreSql)), detect("schema.pgsql", ""));
+        assert_eq!(None, detect("README", "hello"));
+    }
+
+    #[test]
+    fn builtin_specs() {
+        let name = |path| detect(path, "").map(|style| style.name().to_owned());
+        assert_eq!(Some("go".to_owned()), name("cmd/main.go"));
+        assert_eq!(Some("kotlin".to_owned()), name("App.kt"));
+        assert_eq!(Some("kotlin".to_owned()), name("build.gradle.kts"));
+        assert_eq!(Some("swift".to_owned()), name("Sources/main.swift"));
+        assert_eq!(Some("go".to_owned()), name("cmd/main.GO"));
+        assert_eq!(Some("kotlin".to_owned()), name("App.KT"));
+        assert_eq!(Some("kotlin".to_owned()), extension("KTS").map(|style| style.name().to_owned()));
+        assert_eq!(Some("go".to_owned()), detect("", "// vim: ft=go\n").map(|style| style.name().to_owned()));
+    }
+
+    #[test]
+    fn extension_before_content() {
+        assert_eq!(Some(CommentStyle::Python), detect("x.py", "#!/bin/sh\n"));
+    }
+
+    #[test]
+    fn by_shebang() {
+        assert_eq!(Some(CommentStyle::Python), detect("tool", "#!/usr/bin/env python3\n"));
+        assert_eq!(Some(CommentStyle::Python), detect("", "#!/usr/bin/python3.11 -u\n"));
+        assert_eq!(Some(CommentStyle::Shell), detect("", "#!/usr/bin/env -S bash -e\n"));
+        assert_eq!(Some(CommentStyle::Shell), detect("", "#! /bin/sh"));
+        assert_eq!(Some(CommentStyle::JavaScript), detect("", "#!/usr/bin/env node\n"));
+        assert_eq!(None, detect("", "#!/usr/bin/unknown\n"));
+    }
+
+    #[test]
+    fn by_modeline() {
+        assert_eq!(Some(CommentStyle::Python), detect("", "# -*- mode: python; coding: utf-8 -*-\n"));
+        assert_eq!(Some(CommentStyle::C), detect("", "/* -*- C++ -*- */\n"));
+        assert_eq!(Some(CommentStyle::Rust), detect("", "\n// vim: set ts=4 ft=rust:\n"));
+        assert_eq!(Some(CommentStyle::Shell), detect("", "# vi: filetype=sh\n"));
+        assert_eq!(Some(CommentStyle::Sql(SqlDialect::MySql)), detect("", "-- vim: ft=mysql:\n"));
+        assert_eq!(None, detect("", "# -*- coding: utf-8 -*-\n"));
+        assert_eq!(None, detect("", "url = \"http://vim:8080/?ft=rust\"\n"));
+        assert_eq!(None, detect("", "let s = \"vim: ft=rust\";\n"));
+    }
+
+    #[test]
+    fn user_mappings() {
+        let detector = Detector::new()
+            .map("tpl", CommentStyle::XML)
+            .map("h", CommentStyle::Rust)
+            .map("Jenkinsfile", CommentStyle::C);
+        assert_eq!(Some(CommentStyle::XML), detector.detect(Some(Path::new("a.TPL")), b""));
+        assert_eq!(Some(CommentStyle::Rust), detector.detect(Some(Path::new("a.h")), b""));
+        assert_eq!(Some(CommentStyle::C), detector.detect(Some(Path::new("ci/Jenkinsfile")), b""));
+    }
+}
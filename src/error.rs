@@ -0,0 +1,139 @@
+use std::error;
+use std::fmt;
+use std::io;
+use super::Location;
+
+/// What went wrong while finding or removing comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnterminatedComment,
+    OverlappingMatches,
+    MatchOutOfRange,
+    InvalidUtf8,
+    /// XML does not allow `--` inside a comment
+    DoubleHyphenInComment,
+    /// a scanner reached a state it should never be in, names the scanner
+    Parser(&'static str),
+    /// the JSONC style left something that is not strict JSON
+    InvalidJson(String)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            ErrorKind::OverlappingMatches => write!(f, "matches overlapping"),
+            ErrorKind::MatchOutOfRange => write!(f, "match out of range"),
+            ErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ErrorKind::DoubleHyphenInComment => write!(f, "`--` inside comment"),
+            ErrorKind::Parser(style) => write!(f, "{} parser error", style),
+            ErrorKind::InvalidJson(ref message) => write!(f, "invalid JSON: {}", message)
+        }
+    }
+}
+
+/// Error raised by a scanner, only knows the byte offset in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    pub kind: ErrorKind,
+    pub offset: usize
+}
+
+impl ScanError {
+    pub fn new(kind: ErrorKind, offset: usize) -> Self {
+        ScanError { kind, offset }
+    }
+
+    /// Resolve the offset to a full location in `input`
+    pub fn locate(self, input: &str) -> Error {
+        Error::new(self.kind, input, self.offset)
+    }
+}
+
+/// Error with the location in the input where it occurred
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub location: Location
+}
+
+impl Error {
+    /// Offsets past the end or inside a char are moved back to the closest valid position
+    pub fn new(kind: ErrorKind, input: &str, offset: usize) -> Self {
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        Error {
+            kind,
+            location: Location::new(input, offset)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl error::Error for Error {}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum AppError {
+        Io(path: String, err: io::Error) {
+            cause(err)
+            display("error: {}: {}", path, err)
+        }
+        Strip(path: String, err: Error) {
+            cause(err)
+            display("{}:{}:{}: error: {}", path, err.location.line, err.location.column, err)
+        }
+        Walk(message: String) {
+            display("error: {}", message)
+        }
+        UnknownStyle(path: String) {
+            display("error: {}: could not detect the comment style, select one or add a --map", path)
+        }
+        Usage(message: String) {
+            display("error: {}", message)
+        }
+        StyleFile(path: String, err: SpecError) {
+            cause(err)
+            display("error: {}: {}", path, err)
+        }
+    }
+}
+
+quick_error! {
+    /// Why a style spec could not be used
+    #[derive(Debug)]
+    pub enum SpecError {
+        Parse(message: String) {
+            display("{}", message)
+        }
+        Invalid(message: String) {
+            display("invalid style: {}", message)
+        }
+    }
+}
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum StreamError {
+        Read(err: io::Error) {
+            cause(err)
+            display("{}", err)
+        }
+        Write(err: io::Error) {
+            cause(err)
+            display("{}", err)
+        }
+        Strip(err: Error) {
+            from()
+            cause(err)
+            display("{}", err)
+        }
+    }
+}
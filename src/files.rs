@@ -0,0 +1,235 @@
+extern crate ignore;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use self::ignore::WalkBuilder;
+use self::ignore::overrides::{Override, OverrideBuilder};
+use comment_strip::AppError;
+use config::{Config, Target};
+
+/// One input and where its result goes, `None` stands for stdin or stdout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    /// the output is the input file itself
+    pub replace: bool
+}
+
+impl Job {
+    fn new(input: Option<PathBuf>, relative: &Path, target: &Target) -> Self {
+        let output = match *target {
+            Target::Single(ref output) => output.clone(),
+            Target::Directory(ref dir) => Some(dir.join(relative)),
+            Target::InPlace(_) => input.clone()
+        };
+        let replace = match (&input, &output) {
+            (Some(input), Some(output)) => same_file(input, output),
+            _ => false
+        };
+        Job { input, output, replace }
+    }
+
+    pub fn input_name(&self) -> String {
+        name(&self.input, "<stdin>")
+    }
+
+    pub fn output_name(&self) -> String {
+        name(&self.output, "<stdout>")
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false
+    }
+}
+
+fn name(path: &Option<PathBuf>, default: &str) -> String {
+    path.as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| default.to_owned())
+}
+
+/// All files to process, directories are walked recursively honoring `.gitignore`
+/// and the include and exclude globs, files given directly are always processed.
+/// Errors while walking do not stop the search and are returned as well,
+/// two inputs that would be written to the same file fail it.
+pub fn jobs(config: &Config) -> Result<(Vec<Job>, Vec<AppError>), AppError> {
+    if config.inputs.is_empty() {
+        return Ok((vec![Job::new(None, Path::new(""), &config.target)], Vec::new()));
+    }
+    let mut jobs = Vec::new();
+    let mut errors = Vec::new();
+    for input in &config.inputs {
+        if !input.is_dir() {
+            let relative = input.file_name().map(Path::new).unwrap_or(input);
+            jobs.push(Job::new(Some(input.clone()), relative, &config.target));
+            continue;
+        }
+        /* as overrides of the walk the globs would win over `.gitignore` and hidden files */
+        let globs = overrides(config, input)?;
+        let walk = WalkBuilder::new(input)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        for entry in walk {
+            match entry {
+                Ok(ref entry) if entry.file_type().is_some_and(|t| t.is_file())
+                    && !globs.matched(entry.path(), false).is_ignore() => {
+                    let path = entry.path();
+                    let relative = path.strip_prefix(input).unwrap_or(path);
+                    jobs.push(Job::new(Some(path.to_owned()), relative, &config.target));
+                }
+                Ok(_) => (),
+                Err(e) => errors.push(AppError::Walk(e.to_string()))
+            }
+        }
+    }
+    check_outputs(&jobs, &config.target)?;
+    Ok((jobs, errors))
+}
+
+/// Below `--output-dir` files given directly only keep their file name,
+/// so `a/x.c` and `b/x.c` would overwrite each other
+fn check_outputs(jobs: &[Job], target: &Target) -> Result<(), AppError> {
+    if let Target::Directory(_) = *target {
+        let mut inputs: HashMap<&Path, &Path> = HashMap::new();
+        for job in jobs {
+            if let (Some(input), Some(output)) = (&job.input, &job.output) {
+                if let Some(other) = inputs.insert(output, input) {
+                    return Err(AppError::Usage(format!("{} and {} would both be written to {}",
+                                                       other.display(), input.display(), output.display())));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn overrides(config: &Config, root: &Path) -> Result<Override, AppError> {
+    let mut builder = OverrideBuilder::new(root);
+    let globs = config.include.iter().cloned()
+        .chain(config.exclude.iter().map(|g| format!("!{}", g)));
+    for glob in globs {
+        builder.add(&glob)
+            .map_err(|e| AppError::Usage(e.to_string()))?;
+    }
+    builder.build()
+        .map_err(|e| AppError::Usage(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use std::fs;
+    use comment_strip::Detector;
+    use config::{Format, Mode};
+
+    fn config(inputs: Vec<PathBuf>, target: Target, include: &[&str], exclude: &[&str]) -> Config {
+        Config {
+            mode: Mode::Strip,
+            format: Format::Text,
+            batch: inputs.len() > 1,
+            inputs,
+            target,
+            include: include.iter().map(|g| g.to_string()).collect(),
+            exclude: exclude.iter().map(|g| g.to_string()).collect(),
+            style: None,
+            detector: Detector::new(),
+            sql_dialect: None,
+            remove_blanks: true,
+            keep_doc_comments: false,
+            strip_docstrings: false,
+            remove_trailing_commas: false,
+            keep_license_comments: false,
+            keep_kinds: Vec::new(),
+            keep_header: false,
+            keep_matching: Vec::new(),
+            keep_directives: true,
+            directives: Vec::new(),
+            stream: false,
+            preserve_times: false
+        }
+    }
+
+    /// `src/a.c`, `src/sub/b.rs`, `src/.hidden.c`, `src/ignored.c` and `other/a.c`
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in &["src/a.c", "src/sub/b.rs", "src/.hidden.c", "src/ignored.c", "other/a.c"] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(dir.path().join("src/.gitignore"), "ignored.c\n").unwrap();
+        dir
+    }
+
+    fn inputs(jobs: &[Job], root: &Path) -> Vec<PathBuf> {
+        jobs.iter()
+            .map(|job| job.input.as_ref().unwrap().strip_prefix(root).unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn walks_directories() {
+        let dir = tree();
+        let src = dir.path().join("src");
+        let (found, errors) = jobs(&config(vec![src.clone()], Target::Single(None), &[], &[])).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(vec![Path::new("a.c"), Path::new("sub/b.rs")], inputs(&found, &src));
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let dir = tree();
+        let src = dir.path().join("src");
+        let (found, _) = jobs(&config(vec![src.clone()], Target::Single(None), &["*.rs"], &[])).unwrap();
+        assert_eq!(vec![Path::new("sub/b.rs")], inputs(&found, &src));
+        let (found, _) = jobs(&config(vec![src.clone()], Target::Single(None), &[], &["sub/**"])).unwrap();
+        assert_eq!(vec![Path::new("a.c")], inputs(&found, &src));
+    }
+
+    #[test]
+    fn files_given_directly_are_always_processed() {
+        let dir = tree();
+        let ignored = dir.path().join("src/ignored.c");
+        let (found, _) = jobs(&config(vec![ignored.clone()], Target::Single(None), &[], &["*.c"])).unwrap();
+        assert_eq!(vec![Some(ignored)], found.iter().map(|job| job.input.clone()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn output_dir_mapping() {
+        let dir = tree();
+        let out = dir.path().join("out");
+        let config = config(vec![dir.path().join("src"), dir.path().join("src/sub/b.rs")],
+                            Target::Directory(out.clone()), &["*.c"], &[]);
+        let (found, _) = jobs(&config).unwrap();
+        let outputs: Vec<_> = found.iter().map(|job| job.output.clone().unwrap()).collect();
+        assert_eq!(vec![out.join("a.c"), out.join("b.rs")], outputs);
+        assert!(found.iter().all(|job| !job.replace));
+    }
+
+    #[test]
+    fn output_dir_collision() {
+        let dir = tree();
+        let config = config(vec![dir.path().join("src/a.c"), dir.path().join("other/a.c")],
+                            Target::Directory(dir.path().join("out")), &[], &[]);
+        match jobs(&config) {
+            Err(AppError::Usage(message)) => assert!(message.ends_with(&format!("would both be written to {}",
+                                                                                dir.path().join("out/a.c").display()))),
+            other => panic!("expected a usage error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn in_place() {
+        let dir = tree();
+        let file = dir.path().join("src/a.c");
+        let (found, _) = jobs(&config(vec![file.clone()], Target::InPlace(None), &[], &[])).unwrap();
+        assert_eq!(vec![Job { input: Some(file.clone()), output: Some(file), replace: true }], found);
+    }
+}
@@ -0,0 +1,359 @@
+extern crate regex;
+
+use self::regex::Regex;
+use super::{CommentKind, CommentMatch, CommentStyle};
+
+/// Directives of all styles, matched against the comment without delimiters:
+/// editor modelines
+const COMMON_DIRECTIVES: &[&str] = &[
+    r"^-\*-.*-\*-$",
+    r"^(vi|vim|ex):"
+];
+
+/// Comments that mean something to a compiler, linter or template engine
+fn builtin_directives(style: &CommentStyle) -> &'static [&'static str] {
+    match *style {
+        CommentStyle::C => &[
+            r"^@ts-(ignore|expect-error|nocheck|check)\b",
+            r"^(eslint|eslint-disable|eslint-enable|eslint-disable-line|eslint-disable-next-line|jshint|global)\b",
+            r"^prettier-ignore\b",
+            r"^(istanbul|c8) ignore\b",
+            r"^go:\w+",
+            r"^\+build\b",
+            r"^NOLINT(NEXTLINE|BEGIN|END)?\b",
+            r"^clang-format (on|off)\b",
+            r"^[#@]__(PURE|NO_SIDE_EFFECTS|INLINE|NOINLINE)__$",
+            r"^#(pragma|region|endregion)\b",
+            r"^jscpd:ignore-(start|end)\b"
+        ],
+        CommentStyle::JavaScript | CommentStyle::TypeScript | CommentStyle::Tsx => &[
+            r"^@ts-(ignore|expect-error|nocheck|check)\b",
+            r"^(eslint|eslint-disable|eslint-enable|eslint-disable-line|eslint-disable-next-line|jshint|global)\b",
+            r"^prettier-ignore\b",
+            r"^(istanbul|c8) ignore\b",
+            r"^[#@]__(PURE|NO_SIDE_EFFECTS|INLINE|NOINLINE)__$",
+            r"^[#@] source(Mapping)?URL=",
+            r"^/\s*<(reference|amd-module|amd-dependency)\b",
+            r"^@(jsx|jsxFrag|jsxImportSource|jsxRuntime|flow)\b",
+            r"^webpack[A-Z]\w*:",
+            r"^@vite-ignore$",
+            r"^jscpd:ignore-(start|end)\b"
+        ],
+        CommentStyle::Css | CommentStyle::Scss | CommentStyle::Less => &[
+            r"^stylelint-(disable|enable)(-line|-next-line)?\b",
+            r"^prettier-ignore\b",
+            r"^[#@] sourceMappingURL=",
+            r"^rtl:",
+            r"^purgecss (start ignore|end ignore|ignore)$",
+            r"^autoprefixer( grid)?:",
+            r"^csslint\b"
+        ],
+        CommentStyle::Rust => &[
+            r"^ignore-tidy-",
+            r"^(compile-flags|edition|check-pass|run-pass|build-pass):?",
+            r"^~"
+        ],
+        CommentStyle::Shell => &[
+            r"^shellcheck\s+(disable|enable|source|shell|external-sources)=",
+            r"^(ansible-lint|yamllint) (disable|enable)\b"
+        ],
+        CommentStyle::Python => &[
+            r"^type:",
+            r"^noqa\b",
+            r"^(pylint|mypy|pyright|isort):",
+            r"^pragma: no (cover|branch)\b",
+            r"^fmt: (on|off|skip)\b",
+            r"^(-\*- )?(en)?coding[:=]"
+        ],
+        CommentStyle::XML => &[
+            r"^ko\s",
+            r"^/ko$",
+            r"^#(include|echo|set|if|elif|else|endif|config|exec|fsize|flastmod)\b",
+            r"^\[if\s",
+            r"^<!\[endif\]$"
+        ],
+        CommentStyle::Html => &[
+            r"^ko\s",
+            r"^/ko$",
+            r"^#(include|echo|set|if|elif|else|endif|config|exec|fsize|flastmod)\b",
+            r"^\[if\s",
+            r"^<!\[endif\]$",
+            r"^(build|endbuild)\b",
+            r"^htmlmin:ignore$",
+            r"^prettier-ignore\b",
+            r"^(eslint|eslint-disable|eslint-enable|eslint-disable-line|eslint-disable-next-line|global)\b",
+            r"^@ts-(ignore|expect-error|nocheck|check)\b"
+        ],
+        CommentStyle::Sql(_) => &[
+            r"^\+",
+            r"^!\d*",
+            r"^(\+goose|\+migrate)\b",
+            r"^migrate:(up|down)\b",
+            r"^liquibase formatted sql\b",
+            r"^(changeset|rollback|precondition-\w+|preconditions)\b",
+            r"^name: \w+ :\w+"
+        ],
+        CommentStyle::Toml => &[
+            r"^:schema\s"
+        ],
+        CommentStyle::Yaml => &[
+            r"^(yamllint|ansible-lint) (disable|enable)\b",
+            r"^yaml-language-server:",
+            r"^noqa\b",
+            r"^checkov:skip=",
+            r"^kics-scan\b"
+        ],
+        CommentStyle::Custom(ref spec) if spec.name == "go" => &[
+            r"^go:\w+",
+            r"^\+build\b",
+            r"^nolint\b",
+            r"^(lint|revive):\S"
+        ],
+        CommentStyle::Ini | CommentStyle::Properties | CommentStyle::Json | CommentStyle::Custom(_) => &[]
+    }
+}
+
+/// The built-in directives of a style, compiled
+pub fn directives(style: &CommentStyle) -> Vec<Regex> {
+    COMMON_DIRECTIVES.iter()
+        .chain(builtin_directives(style))
+        .map(|pattern| Regex::new(pattern).expect("built-in directive is a valid regex"))
+        .collect()
+}
+
+/// Where the input stands relative to the leading comment block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Header {
+    /// nothing read yet
+    Start,
+    /// a `#` at the very start, might begin a shebang
+    Hash,
+    /// inside the shebang line
+    Shebang,
+    /// only whitespace and the shebang so far
+    Before,
+    /// after a comment of the block, counting the line breaks since
+    Inside(usize),
+    /// the block is over or there was code before any comment
+    Done
+}
+
+fn header_transition(header: Header, c: char) -> Header {
+    match (header, c) {
+        (Header::Start, '#') => Header::Hash,
+        (Header::Hash, '!') => Header::Shebang,
+        (Header::Shebang, '\n') => Header::Before,
+        (Header::Shebang, _) => Header::Shebang,
+        (Header::Start, c) | (Header::Before, c) if c.is_whitespace() => Header::Before,
+        /* a blank line ends the block */
+        (Header::Inside(1), '\n') => Header::Done,
+        (Header::Inside(n), '\n') => Header::Inside(n + 1),
+        (Header::Inside(n), c) if c.is_whitespace() => Header::Inside(n),
+        _ => Header::Done
+    }
+}
+
+/// Length of text at the start of a line that may come before the header like a shebang,
+/// a byte order mark, an XML declaration or a Python encoding line, which is text and not a comment
+fn prelude(text: &str) -> Option<usize> {
+    if text.starts_with('\u{feff}') {
+        return Some('\u{feff}'.len_utf8());
+    }
+    if text.starts_with("<?xml") {
+        return text.find("?>").map(|end| end + 2);
+    }
+    if text.starts_with('#') && !text.starts_with("#!") {
+        let line = &text[..text.find('\n').unwrap_or(text.len())];
+        if line.contains("coding:") || line.contains("coding=") {
+            return Some(line.len());
+        }
+    }
+    None
+}
+
+/// Decides which of the comments found by a scanner are removed.
+/// It has to see the whole input in order, the text between the comments with `text`
+/// and each comment with `comment`, so use a new one for every input.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    style: CommentStyle,
+    keep_kinds: Vec<CommentKind>,
+    keep_header: bool,
+    keep_matching: Vec<Regex>,
+    /// comments matching these are directives
+    directives: Vec<Regex>,
+    header: Header
+}
+
+impl Filter {
+    pub fn new(style: CommentStyle, directives: &[Regex], keep_kinds: &[CommentKind], keep_header: bool,
+               keep_matching: &[Regex]) -> Self {
+        Filter {
+            style,
+            keep_kinds: keep_kinds.to_vec(),
+            keep_header,
+            keep_matching: keep_matching.to_vec(),
+            directives: directives.to_vec(),
+            header: Header::Start
+        }
+    }
+
+    /// Kind of a comment, including the directives matching the patterns
+    fn classify(&self, kind: CommentKind, comment: &str) -> CommentKind {
+        let is_directive = || {
+            let text = self.style.trim_delimiters(comment);
+            self.directives.iter().any(|r| r.is_match(text))
+        };
+        match kind {
+            CommentKind::Line | CommentKind::Block | CommentKind::Doc if is_directive() => CommentKind::Directive,
+            _ => kind
+        }
+    }
+
+    /// Text that is not a comment
+    pub fn text(&mut self, text: &str) {
+        let mut rest = text;
+        while self.header != Header::Done {
+            if let Header::Start | Header::Before = self.header {
+                if let Some(len) = prelude(rest) {
+                    self.header = Header::Before;
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            let mut chars = rest.chars();
+            match chars.next() {
+                Some(c) => self.header = header_transition(self.header, c),
+                None => break
+            }
+            rest = chars.as_str();
+        }
+    }
+
+    /// The classified match if it is removed, `None` if the comment is kept
+    pub fn comment(&mut self, m: CommentMatch, comment: &str) -> Option<CommentMatch> {
+        let in_header = match self.header {
+            Header::Start | Header::Before | Header::Inside(_) => {
+                self.header = Header::Inside(0);
+                true
+            }
+            Header::Hash | Header::Shebang | Header::Done => false
+        };
+        let kind = self.classify(m.kind, comment);
+        if (in_header && self.keep_header)
+            || self.keep_kinds.contains(&kind)
+            || self.keep_matching.iter().any(|r| r.is_match(comment)) {
+            None
+        } else {
+            Some(CommentMatch { kind, ..m })
+        }
+    }
+
+    /// Only the removed comments of a complete input
+    pub fn apply(&mut self, input: &str, matches: Vec<CommentMatch>) -> Vec<CommentMatch> {
+        let mut position = 0;
+        let mut removed = Vec::with_capacity(matches.len());
+        for m in matches {
+            self.text(&input[position..m.from]);
+            removed.extend(self.comment(m, &input[m.from..m.to]));
+            position = m.to;
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{SqlDialect, Stripper};
+
+    /// All comments, without the directives kept by default
+    fn find_comments(input: &str, style: &CommentStyle) -> Vec<CommentMatch> {
+        Stripper::new(style.clone()).keep_directives(false).find_comments(input).unwrap()
+    }
+
+    fn kept(style: CommentStyle, input: &str, keep_header: bool, keep_matching: &[&str]) -> Vec<String> {
+        let patterns: Vec<Regex> = keep_matching.iter().map(|p| Regex::new(p).unwrap()).collect();
+        let matches = find_comments(input, &style);
+        let removed = Filter::new(style, &[], &[], keep_header, &patterns).apply(input, matches.clone());
+        matches.iter()
+            .filter(|m| !removed.iter().any(|r| r.from == m.from))
+            .map(|m| input[m.from..m.to].to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_header() {
+        let input = "\n/* license */\n// more\n\n// not header\nint a; /* b */\n";
+        assert_eq!(vec!["/* license */", "// more"], kept(CommentStyle::C, input, true, &[]));
+        assert_eq!(Vec::<String>::new(), kept(CommentStyle::C, input, false, &[]));
+        assert_eq!(Vec::<String>::new(), kept(CommentStyle::C, "int a; // x\n", true, &[]));
+    }
+
+    #[test]
+    fn keeps_header_after_shebang() {
+        let input = "#!/bin/sh # not header\n# Copyright\necho # x\n";
+        assert_eq!(vec!["# Copyright"], kept(CommentStyle::Shell, input, true, &[]));
+    }
+
+    #[test]
+    fn keeps_header_after_xml_declaration() {
+        let input = "<?xml version=\"1.0\"?>\n<!-- Copyright -->\n<a/><!-- x -->\n";
+        assert_eq!(vec!["<!-- Copyright -->"], kept(CommentStyle::XML, input, true, &[]));
+    }
+
+    #[test]
+    fn keeps_header_after_byte_order_mark() {
+        let input = "\u{feff}/* Copyright */\nint a; /* x */\n";
+        assert_eq!(vec!["/* Copyright */"], kept(CommentStyle::C, input, true, &[]));
+    }
+
+    #[test]
+    fn keeps_header_after_encoding_line() {
+        let input = "#!/usr/bin/env python\n# -*- coding: utf-8 -*-\n# Copyright\nimport os  # x\n";
+        assert_eq!(vec!["# Copyright"], kept(CommentStyle::Python, input, true, &[]));
+        let input = "# vim: set fileencoding=utf-8 :\n\n# Copyright\na = 1  # x\n";
+        assert_eq!(vec!["# Copyright"], kept(CommentStyle::Python, input, true, &[]));
+    }
+
+    fn directives_kept(style: CommentStyle, input: &str) -> Vec<String> {
+        let matches = find_comments(input, &style);
+        let removed = Filter::new(style.clone(), &directives(&style), &[CommentKind::Directive], false, &[])
+            .apply(input, matches.clone());
+        matches.iter()
+            .filter(|m| !removed.iter().any(|r| r.from == m.from))
+            .map(|m| input[m.from..m.to].to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_directives() {
+        let input = "// @ts-ignore\nx(); // eslint-disable-line no-console\n//go:build linux\n                     /* vim: set ts=4: */ /* just a comment */ // ts-ignore me\n// jscpd:ignore-start\n";
+        assert_eq!(vec!["// @ts-ignore", "// eslint-disable-line no-console", "//go:build linux",
+                        "/* vim: set ts=4: */", "// jscpd:ignore-start"],
+                   directives_kept(CommentStyle::C, input));
+        let input = "import a  # noqa: F401\nb = 1  # type: ignore[x]\n# note\nB = 2  # pylint: disable=C0103\n";
+        assert_eq!(vec!["# noqa: F401", "# type: ignore[x]", "# pylint: disable=C0103"],
+                   directives_kept(CommentStyle::Python, input));
+        let input = "/// <reference types=\"node\" />\nimport(/* webpackChunkName: \"a\" */ './a'); // load\n//# sourceMappingURL=a.js.map\n";
+        assert_eq!(vec!["/// <reference types=\"node\" />", "/* webpackChunkName: \"a\" */", "//# sourceMappingURL=a.js.map"],
+                   directives_kept(CommentStyle::TypeScript, input));
+        let input = "a { /* stylelint-disable-next-line color-named */ color: red; } // red\n/*# sourceMappingURL=a.css.map */";
+        assert_eq!(vec!["/* stylelint-disable-next-line color-named */", "/*# sourceMappingURL=a.css.map */"],
+                   directives_kept(CommentStyle::Scss, input));
+        let input = "<!-- ko if: a --><b/><!-- /ko --><!-- comment -->";
+        assert_eq!(vec!["<!-- ko if: a -->", "<!-- /ko -->"], directives_kept(CommentStyle::XML, input));
+        let input = "# shellcheck disable=SC2086\necho $a # shellcheck is great\n";
+        assert_eq!(vec!["# shellcheck disable=SC2086"], directives_kept(CommentStyle::Shell, input));
+        let input = "-- +goose Up\n-- name: GetAuthor :one\nSELECT /*+ INDEX(a) */ 1; /*!40101 SET x */ -- plus\n";
+        assert_eq!(vec!["-- +goose Up", "-- name: GetAuthor :one", "/*+ INDEX(a) */", "/*!40101 SET x */"],
+                   directives_kept(CommentStyle::Sql(SqlDialect::MySql), input));
+    }
+
+    #[test]
+    fn keeps_matching() {
+        let input = "a; // SPDX-License-Identifier: MIT\n/*! keep */ /* drop */\n";
+        assert_eq!(vec!["// SPDX-License-Identifier: MIT", "/*! keep */"],
+                   kept(CommentStyle::C, input, false, &["SPDX-License-Identifier", r"^/\*!"]));
+    }
+}
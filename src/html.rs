@@ -0,0 +1,547 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+use super::{css, js};
+
+/// Elements whose content is not parsed as HTML
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    /// scanned for JavaScript comments
+    Script,
+    /// `<script>` of another type like `text/template`, text only
+    DataBlock,
+    /// scanned for CSS comments
+    Style,
+    /// text only, `<!-- -->` inside is content
+    Textarea,
+    Title
+}
+
+impl Element {
+    const ALL: [Element; 4] = [Element::Script, Element::Style, Element::Textarea, Element::Title];
+
+    fn end_tag(&self) -> &'static str {
+        match *self {
+            Element::Script | Element::DataBlock => "</script",
+            Element::Style => "</style",
+            Element::Textarea => "</textarea",
+            Element::Title => "</title"
+        }
+    }
+
+    fn from_name(name: TagName) -> Option<Element> {
+        Element::ALL.iter()
+            .find(|e| e.end_tag()[2..].chars().fold(TagName::empty(), TagName::push) == name)
+            .cloned()
+    }
+
+    /// How much of the end tag is matched after reading `c`
+    fn end_tag_progress(&self, matched: usize, c: char) -> usize {
+        match self.end_tag().as_bytes().get(matched) {
+            Some(&b) if c.to_ascii_lowercase() == b as char => matched + 1,
+            _ if c == '<' => 1,
+            _ => 0
+        }
+    }
+}
+
+/// Values of the `type` attribute of a script that mean JavaScript, no attribute or an empty value does too
+const JS_TYPES: &[&str] = &["text/javascript", "application/javascript", "module", "text/ecmascript",
+                            "application/ecmascript", "application/x-javascript"];
+
+/// Value of the `type` attribute of a script, compared with `JS_TYPES` while it is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScriptType {
+    /// bit set of the types the value may still be
+    candidates: u8,
+    len: usize
+}
+
+impl ScriptType {
+    fn empty() -> Self {
+        ScriptType { candidates: (1 << JS_TYPES.len()) - 1, len: 0 }
+    }
+
+    /// Whitespace around the value is ignored, and inside it, which makes no difference
+    fn push(self, c: char) -> Self {
+        if c.is_whitespace() {
+            return self;
+        }
+        let c = c.to_ascii_lowercase();
+        let candidates = JS_TYPES.iter().enumerate()
+            .filter(|&(i, t)| self.candidates & 1 << i != 0 && t[self.len..].starts_with(c))
+            .fold(0, |set, (i, _)| set | 1 << i);
+        ScriptType { candidates, len: self.len + c.len_utf8() }
+    }
+
+    /// The element whose content follows
+    fn element(&self) -> Element {
+        let is_js = self.len == 0 || JS_TYPES.iter().enumerate()
+            .any(|(i, t)| self.candidates & 1 << i != 0 && t.len() == self.len);
+        if is_js { Element::Script } else { Element::DataBlock }
+    }
+}
+
+/// Lowercase tag name packed into an integer, long enough for the names of all elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TagName {
+    bytes: u64,
+    len: usize
+}
+
+impl TagName {
+    fn empty() -> Self {
+        TagName { bytes: 0, len: 0 }
+    }
+
+    fn push(self, c: char) -> Self {
+        if self.len < 8 && c.is_ascii() {
+            TagName { bytes: self.bytes << 8 | c.to_ascii_lowercase() as u64, len: self.len + 1 }
+        } else {
+            TagName { bytes: 0, len: 9 }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseState {
+    /// quotes in text are just text
+    Text,
+    /// `<`, might start a tag or a comment
+    TagOpen,
+    TagName(TagName),
+    /// attributes of a start tag, remembering the element it opens
+    InTag(Option<Element>),
+    AttributeName(Option<Element>, TagName),
+    /// whitespace after an attribute name, which a `=` may still follow
+    AfterAttributeName(Option<Element>, TagName),
+    /// after `=`, where a quoted value may start
+    BeforeValue(Option<Element>),
+    /// after the `=` of the `type` attribute of a script
+    BeforeScriptType,
+    /// value of the `type` attribute of a script and its quote, if any
+    ScriptType(Option<char>, ScriptType),
+    DoubleQuotedValue(Option<Element>),
+    SingleQuotedValue(Option<Element>),
+    EndTag,
+    /// `<!`
+    MarkupOpen,
+    /// `<!-`
+    CommentOpenDash,
+    /// doctype or processing instruction, until `>`
+    Declaration,
+    /// right after `<!--`, where `>` or `->` end an empty comment
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentEndDash,
+    CommentEnd,
+    /// `--!`
+    CommentEndBang,
+    /// script content with the state of the embedded scanner and how much of the end tag is matched
+    Script(js::ParseState, usize),
+    Style(css::ParseState, usize),
+    RawText(Element, usize),
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Text
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentMightStart,
+    CommentConfirmed,
+    CommentDismissed,
+    /// comment ends after the current char
+    CommentEnds,
+    CommentUnterminated,
+    Script(js::ParseAction),
+    Style(css::ParseAction),
+    /// the end tag of a script or style starts this many bytes before the current char
+    EmbeddedEnds(usize)
+}
+
+/// After the `>` of a start tag
+fn content(element: Option<Element>) -> ParseState {
+    match element {
+        None => ParseState::Text,
+        Some(Element::Script) => ParseState::Script(js::ParseState::start(), 0),
+        Some(Element::Style) => ParseState::Style(css::ParseState::start(), 0),
+        Some(element) => ParseState::RawText(element, 0)
+    }
+}
+
+fn in_tag(element: Option<Element>, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '>'     => (content(element), ParseAction::Nothing),
+        '='     => (ParseState::BeforeValue(element), ParseAction::Nothing),
+        c if c.is_whitespace() || c == '/'
+                => (ParseState::InTag(element), ParseAction::Nothing),
+        _       => (ParseState::AttributeName(element, TagName::empty().push(c)), ParseAction::Nothing)
+    }
+}
+
+/// After the `=` of an attribute
+fn before_value(element: Option<Element>, name: TagName) -> ParseState {
+    let is_type = name == "type".chars().fold(TagName::empty(), TagName::push);
+    match element {
+        Some(Element::Script) | Some(Element::DataBlock) if is_type => ParseState::BeforeScriptType,
+        _ => ParseState::BeforeValue(element)
+    }
+}
+
+fn is_end_of_tag_name(c: char) -> bool {
+    c.is_whitespace() || c == '/' || c == '>'
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::Text => match c {
+                '<'     => (ParseState::TagOpen, ParseAction::CommentMightStart),
+                _       => (ParseState::Text, ParseAction::Nothing)
+            },
+            ParseState::TagOpen => match c {
+                '!'     => (ParseState::MarkupOpen, ParseAction::Nothing),
+                '<'     => (ParseState::TagOpen, ParseAction::CommentMightStart),
+                '/'     => (ParseState::EndTag, ParseAction::CommentDismissed),
+                '?'     => (ParseState::Declaration, ParseAction::CommentDismissed),
+                c if c.is_alphabetic() => (ParseState::TagName(TagName::empty().push(c)), ParseAction::CommentDismissed),
+                _       => (ParseState::Text, ParseAction::CommentDismissed)
+            },
+            ParseState::TagName(name) => match c {
+                c if is_end_of_tag_name(c) => in_tag(Element::from_name(name), c),
+                _       => (ParseState::TagName(name.push(c)), ParseAction::Nothing)
+            },
+            ParseState::InTag(element) => in_tag(element, c),
+            ParseState::AttributeName(element, name) => match c {
+                '='     => (before_value(element, name), ParseAction::Nothing),
+                c if c.is_whitespace()
+                        => (ParseState::AfterAttributeName(element, name), ParseAction::Nothing),
+                '>' | '/'
+                        => in_tag(element, c),
+                _       => (ParseState::AttributeName(element, name.push(c)), ParseAction::Nothing)
+            },
+            ParseState::AfterAttributeName(element, name) => match c {
+                '='     => (before_value(element, name), ParseAction::Nothing),
+                c if c.is_whitespace()
+                        => (ParseState::AfterAttributeName(element, name), ParseAction::Nothing),
+                _       => in_tag(element, c)
+            },
+            ParseState::BeforeValue(element) => match c {
+                '"'     => (ParseState::DoubleQuotedValue(element), ParseAction::Nothing),
+                '\''    => (ParseState::SingleQuotedValue(element), ParseAction::Nothing),
+                c if c.is_whitespace() => (ParseState::BeforeValue(element), ParseAction::Nothing),
+                _       => in_tag(element, c)
+            },
+            ParseState::BeforeScriptType => match c {
+                '"' | '\''
+                        => (ParseState::ScriptType(Some(c), ScriptType::empty()), ParseAction::Nothing),
+                c if c.is_whitespace() => (ParseState::BeforeScriptType, ParseAction::Nothing),
+                '>'     => in_tag(Some(Element::Script), c),
+                _       => (ParseState::ScriptType(None, ScriptType::empty().push(c)), ParseAction::Nothing)
+            },
+            ParseState::ScriptType(Some(quote), value) => match c {
+                c if c == quote => (ParseState::InTag(Some(value.element())), ParseAction::Nothing),
+                _       => (ParseState::ScriptType(Some(quote), value.push(c)), ParseAction::Nothing)
+            },
+            ParseState::ScriptType(None, value) => match c {
+                c if c.is_whitespace() || c == '>'
+                        => in_tag(Some(value.element()), c),
+                _       => (ParseState::ScriptType(None, value.push(c)), ParseAction::Nothing)
+            },
+            ParseState::DoubleQuotedValue(element) => match c {
+                '"'     => (ParseState::InTag(element), ParseAction::Nothing),
+                _       => (ParseState::DoubleQuotedValue(element), ParseAction::Nothing)
+            },
+            ParseState::SingleQuotedValue(element) => match c {
+                '\''    => (ParseState::InTag(element), ParseAction::Nothing),
+                _       => (ParseState::SingleQuotedValue(element), ParseAction::Nothing)
+            },
+            ParseState::EndTag | ParseState::Declaration => match c {
+                '>'     => (ParseState::Text, ParseAction::Nothing),
+                _       => (from, ParseAction::Nothing)
+            },
+            ParseState::MarkupOpen => match c {
+                '-'     => (ParseState::CommentOpenDash, ParseAction::Nothing),
+                _       => (ParseState::Declaration, ParseAction::CommentDismissed)
+            },
+            ParseState::CommentOpenDash => match c {
+                '-'     => (ParseState::CommentStart, ParseAction::CommentConfirmed),
+                _       => (ParseState::Declaration, ParseAction::CommentDismissed)
+            },
+            ParseState::CommentStart => match c {
+                '>'     => (ParseState::Text, ParseAction::CommentEnds),
+                '-'     => (ParseState::CommentStartDash, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::CommentStartDash => match c {
+                '>'     => (ParseState::Text, ParseAction::CommentEnds),
+                '-'     => (ParseState::CommentEnd, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::Comment => match c {
+                '-'     => (ParseState::CommentEndDash, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::CommentEndDash => match c {
+                '-'     => (ParseState::CommentEnd, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::CommentEnd => match c {
+                '>'     => (ParseState::Text, ParseAction::CommentEnds),
+                '!'     => (ParseState::CommentEndBang, ParseAction::Nothing),
+                '-'     => (ParseState::CommentEnd, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::CommentEndBang => match c {
+                '>'     => (ParseState::Text, ParseAction::CommentEnds),
+                '-'     => (ParseState::CommentEndDash, ParseAction::Nothing),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            /* the end tag ends the content even inside a string or comment of the embedded language */
+            ParseState::Script(state, matched) => match c {
+                c if matched == Element::Script.end_tag().len() && is_end_of_tag_name(c) =>
+                    (in_tag(None, c).0, ParseAction::EmbeddedEnds(matched)),
+                _ => {
+                    let (state, action) = js::state_transition(js::Syntax::Plain, state, current_char);
+                    (ParseState::Script(state, Element::Script.end_tag_progress(matched, c)), ParseAction::Script(action))
+                }
+            },
+            ParseState::Style(state, matched) => match c {
+                c if matched == Element::Style.end_tag().len() && is_end_of_tag_name(c) =>
+                    (in_tag(None, c).0, ParseAction::EmbeddedEnds(matched)),
+                _ => {
+                    let (state, action) = css::state_transition(false, state, current_char);
+                    (ParseState::Style(state, Element::Style.end_tag_progress(matched, c)), ParseAction::Style(action))
+                }
+            },
+            ParseState::RawText(element, matched) => match c {
+                c if matched == element.end_tag().len() && is_end_of_tag_name(c) => in_tag(None, c),
+                _ => (ParseState::RawText(element, element.end_tag_progress(matched, c)), ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::TagOpen | ParseState::MarkupOpen | ParseState::CommentOpenDash =>
+                (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::CommentStart |
+            ParseState::CommentStartDash |
+            ParseState::Comment |
+            ParseState::CommentEndDash |
+            ParseState::CommentEnd |
+            ParseState::CommentEndBang =>
+                (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::Script(state, _) =>
+                (ParseState::End, ParseAction::Script(js::state_transition(js::Syntax::Plain, state, None).1)),
+            ParseState::Style(state, _) =>
+                (ParseState::End, ParseAction::Style(css::state_transition(false, state, None).1)),
+            _ =>
+                (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+enum CommentState {
+    NotInComment,
+    MaybeInComment(usize),
+    InComment(usize),
+    /// inside a script or style, with the state of the embedded scanner
+    InScript(js::CommentState),
+    InStyle(css::CommentState)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from) => Some(from),
+            CommentState::InScript(state) => state.pending(),
+            CommentState::InStyle(state) => state.pending()
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentMightStart => {
+            comment_state = CommentState::MaybeInComment(position);
+        },
+        ParseAction::CommentConfirmed => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    comment_state = CommentState::InComment(from);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("html style"), position));
+                }
+            }
+        },
+        ParseAction::CommentDismissed => {
+            comment_state = CommentState::NotInComment;
+        },
+        ParseAction::CommentEnds => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    matches.push(CommentMatch { from, to: position + 1, kind: CommentKind::Block });
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("html style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("html style"), position));
+                }
+            }
+        },
+        ParseAction::Script(action) => {
+            let state = match comment_state {
+                CommentState::InScript(state) => state,
+                _ => js::CommentState::start()
+            };
+            let (state, found) = js::do_action(action, state, position, matches)?;
+            comment_state = CommentState::InScript(state);
+            matches = found;
+        },
+        ParseAction::Style(action) => {
+            let state = match comment_state {
+                CommentState::InStyle(state) => state,
+                _ => css::CommentState::start()
+            };
+            let (state, found) = css::do_action(action, state, position, matches)?;
+            comment_state = CommentState::InStyle(state);
+            matches = found;
+        },
+        ParseAction::EmbeddedEnds(len) => {
+            /* a comment still open ends with the end tag */
+            let to = position - len;
+            match comment_state {
+                CommentState::InScript(js::CommentState::InComment(from, kind)) |
+                CommentState::InStyle(css::CommentState::InComment(from, kind)) => {
+                    matches.push(CommentMatch { from, to, kind });
+                },
+                _ => {}
+            }
+            comment_state = CommentState::NotInComment;
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition, do_action)
+}
+
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::comment_texts;
+
+    #[test]
+    fn html_comments() {
+        let input = "<p>a</p><!-- b --><!--><!---><br/><!-- c --!>";
+        let expected = Ok(vec![
+            CommentMatch { from: 8, to: 18, kind: CommentKind::Block },
+            CommentMatch { from: 18, to: 23, kind: CommentKind::Block },
+            CommentMatch { from: 23, to: 29, kind: CommentKind::Block },
+            CommentMatch { from: 34, to: 45, kind: CommentKind::Block }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn quotes_only_in_tags() {
+        let input = "<p title='<!-- no -->'>don't <!-- yes --></p><!DOCTYPE html><a href=x'y><!-- too -->";
+        let expected = vec![
+            ("<!-- yes -->", CommentKind::Block),
+            ("<!-- too -->", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input)));
+    }
+
+    #[test]
+    fn script_and_style() {
+        let input = "<script type=\"module\">let a = '<!-- no -->', r = /[/*]/; // js\n/* b */</script>\
+                     <style>a { background: url(//x) } /* css */</style><!-- html -->";
+        let expected = vec![
+            ("// js", CommentKind::Line),
+            ("/* b */", CommentKind::Block),
+            ("/* css */", CommentKind::Block),
+            ("<!-- html -->", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input)));
+    }
+
+    #[test]
+    fn only_javascript_in_scripts() {
+        let input = "<script type=\"text/template\"><p>it's // <%= a %></p></script><!-- a -->\
+                     <script type='text/x-handlebars'>{{! /* b */}}</script>\
+                     <script type = Application/JavaScript>// c\n</script><script type=\"\">// d\n</script>\
+                     <script async type=\"text/javascript; charset=utf-8\">/* e */</script>\
+                     <script src=x.js>// f\n</script>";
+        let expected = vec![
+            ("<!-- a -->", CommentKind::Block),
+            ("// c", CommentKind::Line),
+            ("// d", CommentKind::Line),
+            ("// f", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input)));
+    }
+
+    #[test]
+    fn end_tag_ends_embedded_comments() {
+        let input = "<SCRIPT>x // y </Script >z<!-- a --><style>/* b </style><!-- c -->";
+        let expected = vec![
+            ("// y ", CommentKind::Line),
+            ("<!-- a -->", CommentKind::Block),
+            ("/* b ", CommentKind::Block),
+            ("<!-- c -->", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input)));
+    }
+
+    #[test]
+    fn raw_text() {
+        let input = "<textarea><!-- kept --></textarea><title>a <!-- b --></title ><!-- c -->";
+        assert_eq!(vec![("<!-- c -->", CommentKind::Block)], comment_texts(input, find_comments(input)));
+    }
+
+    #[test]
+    fn unterminated() {
+        let error = find_comments("<p><!-- a").unwrap_err();
+        assert_eq!(ErrorKind::UnterminatedComment, error.kind);
+        assert_eq!(3, error.location.offset);
+    }
+}
@@ -0,0 +1,170 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    /// only whitespace so far on this line, `;` and `#` start a comment
+    LineStart,
+    Normal,
+    /// whitespace in a value, `;` starts a comment, `#` does not as in `color = #fff`
+    Whitespace,
+    Comment,
+    DoubleQuotes,
+    DoubleQuotesEscaped,
+    /// a backslash outside quotes, before a line break it continues the value
+    Escaped,
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::LineStart
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentStarts,
+    CommentEnds
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\n'    => (ParseState::LineStart, ParseAction::Nothing),
+        '"'     => (ParseState::DoubleQuotes, ParseAction::Nothing),
+        '\\'    => (ParseState::Escaped, ParseAction::Nothing),
+        c if c.is_whitespace() => (ParseState::Whitespace, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Nothing)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::LineStart => match c {
+                ';' | '#' => (ParseState::Comment, ParseAction::CommentStarts),
+                c if c.is_whitespace() => (ParseState::LineStart, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::Normal => normal(c),
+            ParseState::Whitespace => match c {
+                ';'     => (ParseState::Comment, ParseAction::CommentStarts),
+                _       => normal(c)
+            },
+            ParseState::Comment => match c {
+                '\n'    => (ParseState::LineStart, ParseAction::CommentEnds),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::DoubleQuotes => match c {
+                '"'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::DoubleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::DoubleQuotes, ParseAction::Nothing)
+            },
+            ParseState::DoubleQuotesEscaped =>
+                (ParseState::DoubleQuotes, ParseAction::Nothing),
+            ParseState::Escaped => match c {
+                /* the next line is part of the value and never a comment */
+                '\r'    => (ParseState::Escaped, ParseAction::Nothing),
+                _       => (ParseState::Normal, ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::Comment => (ParseState::End, ParseAction::CommentEnds),
+            _ => (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentState {
+    NotInComment,
+    InComment(usize)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentStarts => {
+            comment_state = CommentState::InComment(position);
+        },
+        ParseAction::CommentEnds => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    matches.push(CommentMatch { from, to: position, kind: CommentKind::Line });
+                    comment_state = CommentState::NotInComment;
+                },
+                CommentState::NotInComment => {
+                    return Err(ScanError::new(ErrorKind::Parser("ini style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition, do_action)
+}
+
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_comments() {
+        let input = "; one\n[section] ; two\n  # three\nkey=value\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 5, kind: CommentKind::Line },
+            CommentMatch { from: 16, to: 21, kind: CommentKind::Line },
+            CommentMatch { from: 24, to: 31, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn no_comment_in_values() {
+        let input = "color = #fff\nurl=a;b\nname = \"x ; \\\" ; y\" ; z";
+        let expected = Ok(vec![
+            CommentMatch { from: 41, to: 44, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn line_continuation() {
+        let input = "key = a \\\n; still the value\n; comment\nkey = b \\\r\n# value\r\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 28, to: 37, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+}
@@ -0,0 +1,695 @@
+extern crate regex;
+
+use self::regex::Regex;
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// Keywords after which an expression, and so a regex, may follow
+const KEYWORDS: &[&str] = &["return", "typeof", "instanceof", "in", "of", "new", "delete", "void",
+                            "throw", "case", "do", "else", "yield", "await"];
+
+/// Which variant of the language is read, they differ in what a `<` may start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// no JSX, as in TypeScript where `<T>x` is a type assertion and `x!` a non-null assertion,
+    /// or in an HTML `<script>`
+    Plain,
+    /// JSX elements where an expression may start
+    Jsx,
+    /// JSX, but `<T,>`, `<T = U>` and `<T extends U>` start the type parameters of an arrow function,
+    /// and `x!` is a non-null assertion
+    Tsx
+}
+
+/// What came before, deciding whether a `/` divides or starts a regex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    /// an operator, punctuation or keyword, a regex may follow
+    Operator,
+    /// an identifier, literal or closing bracket, a `/` divides
+    Operand,
+    /// a single `+`, a second one makes an operand as in `i++ / 2`
+    Plus,
+    Minus,
+    /// `.`, the next word is a property and never a keyword
+    Dot
+}
+
+/// Identifier packed into an integer, long enough for all keywords
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Word {
+    bytes: u128,
+    len: usize
+}
+
+impl Word {
+    fn empty() -> Self {
+        Word { bytes: 0, len: 0 }
+    }
+
+    fn push(self, c: char) -> Self {
+        if self.len < 16 && c.is_ascii() {
+            Word { bytes: self.bytes << 8 | c as u128, len: self.len + 1 }
+        } else {
+            Word { bytes: 0, len: 17 }
+        }
+    }
+
+    fn is(&self, word: &str) -> bool {
+        word.chars().fold(Word::empty(), Word::push) == *self
+    }
+
+    fn is_keyword(&self) -> bool {
+        KEYWORDS.iter().any(|k| self.is(k))
+    }
+}
+
+/// What a `}` closes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frame {
+    Brace,
+    /// `${` in a template literal
+    Substitution,
+    /// `{` between the children of a JSX element
+    ChildExpression,
+    /// `{` as a JSX attribute value
+    AttributeExpression,
+    /// open JSX element, its children are text
+    Element
+}
+
+/// Stack of frames, only used inside templates and JSX
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Nesting {
+    frames: Vec<Frame>
+}
+
+impl Nesting {
+    fn empty() -> Self {
+        Nesting { frames: Vec::new() }
+    }
+
+    fn push(mut self, frame: Frame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    fn pop(mut self) -> Self {
+        self.frames.pop();
+        self
+    }
+
+    fn top(&self) -> Option<Frame> {
+        self.frames.last().cloned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Start,
+    /// `#!` line at the start of a script
+    Shebang,
+    Normal(Token),
+    Word(Word),
+    Property,
+    /// `/`, might start a comment or a regex, or divide
+    FirstSlash(Token),
+    SecondSlash(Token),
+    /// `///`, a doc comment unless a fourth slash follows
+    ThirdSlash(Token),
+    LineComment(Token),
+    /// `/*`, might be followed by the second star of a doc comment
+    BlockCommentStart(Token),
+    /// `/**`, a doc comment unless it is `/**/` or another star follows
+    BlockDocStart(Token),
+    BlockComment(Token),
+    BlockCommentStar(Token),
+    String(char),
+    StringEscaped(char),
+    Template,
+    TemplateEscaped,
+    TemplateDollar,
+    Regex,
+    RegexEscaped,
+    /// `[...]` in a regex, where `/` does not end it
+    RegexClass,
+    RegexClassEscaped,
+    /// `<` where an expression may start
+    JsxOpen,
+    /// name of a JSX start tag, or of the first type parameter in TSX
+    JsxName,
+    /// word after the name in TSX, `extends` starts type parameters
+    JsxNameSpace(Word),
+    /// name and attributes of a JSX start tag
+    JsxTag,
+    JsxTagSlash,
+    JsxAttribute(char),
+    /// children of a JSX element, quotes and slashes are just text
+    JsxText,
+    JsxTextOpen,
+    JsxClosingTag,
+    End
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseState {
+    mode: Mode,
+    nesting: Nesting
+}
+
+impl ParseState {
+    fn new(mode: Mode, nesting: Nesting) -> Self {
+        ParseState { mode, nesting }
+    }
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::new(Mode::Start, Nesting::empty())
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::new(Mode::End, Nesting::empty())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseAction {
+    Nothing,
+    CommentMightStart,
+    CommentConfirmed(CommentKind),
+    /// the comment turns out to be of another kind, like `///` is a doc comment
+    CommentKindChanged(CommentKind),
+    CommentDismissed,
+    /// comment ends before the current char
+    CommentEnds,
+    /// comment ends after the current char
+    BlockCommentEnds,
+    CommentUnterminated
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '#'
+}
+
+fn open(nesting: Nesting, frame: Frame, mode: Mode) -> (ParseState, ParseAction) {
+    (ParseState::new(mode, nesting.push(frame)), ParseAction::Nothing)
+}
+
+fn close(nesting: Nesting) -> ParseState {
+    let mode = match nesting.top() {
+        Some(Frame::Substitution) => Mode::Template,
+        Some(Frame::ChildExpression) => Mode::JsxText,
+        Some(Frame::AttributeExpression) => Mode::JsxTag,
+        _ => Mode::Normal(Token::Operator)
+    };
+    ParseState::new(mode, nesting.pop())
+}
+
+/// After a JSX element is closed, `nesting` does not contain it anymore
+fn after_element(nesting: Nesting) -> ParseState {
+    match nesting.top() {
+        Some(Frame::Element) => ParseState::new(Mode::JsxText, nesting),
+        _ => ParseState::new(Mode::Normal(Token::Operand), nesting)
+    }
+}
+
+fn normal(syntax: Syntax, token: Token, nesting: Nesting, c: char) -> (ParseState, ParseAction) {
+    let mode = match c {
+        '/'     => return (ParseState::new(Mode::FirstSlash(token), nesting), ParseAction::CommentMightStart),
+        '{' if !nesting.frames.is_empty()
+                => return open(nesting, Frame::Brace, Mode::Normal(Token::Operator)),
+        '}'     => return (close(nesting), ParseAction::Nothing),
+        '"' | '\''
+                => Mode::String(c),
+        '`'     => Mode::Template,
+        ')' | ']'
+                => Mode::Normal(Token::Operand),
+        /* non-null assertion like `obj!` in TypeScript */
+        '!' if syntax != Syntax::Jsx && token == Token::Operand
+                => Mode::Normal(Token::Operand),
+        '+' if token == Token::Plus
+                => Mode::Normal(Token::Operand),
+        '+'     => Mode::Normal(Token::Plus),
+        '-' if token == Token::Minus
+                => Mode::Normal(Token::Operand),
+        '-'     => Mode::Normal(Token::Minus),
+        '.'     => Mode::Normal(Token::Dot),
+        '<' if syntax != Syntax::Plain && token != Token::Operand
+                => Mode::JsxOpen,
+        c if c.is_whitespace()
+                => Mode::Normal(token),
+        c if is_identifier(c) && token == Token::Dot
+                => Mode::Property,
+        c if is_identifier(c)
+                => Mode::Word(Word::empty().push(c)),
+        _       => Mode::Normal(Token::Operator)
+    };
+    (ParseState::new(mode, nesting), ParseAction::Nothing)
+}
+
+fn regex(nesting: Nesting, c: char) -> ParseState {
+    let mode = match c {
+        '/'     => Mode::Normal(Token::Operand),
+        '\\'    => Mode::RegexEscaped,
+        '['     => Mode::RegexClass,
+        '\n'    => Mode::Normal(Token::Operand),
+        _       => Mode::Regex
+    };
+    ParseState::new(mode, nesting)
+}
+
+fn jsx_tag(nesting: Nesting, c: char) -> (ParseState, ParseAction) {
+    let mode = match c {
+        '"' | '\''
+                => Mode::JsxAttribute(c),
+        '{'     => return open(nesting, Frame::AttributeExpression, Mode::Normal(Token::Operator)),
+        '/'     => Mode::JsxTagSlash,
+        '>'     => return open(nesting, Frame::Element, Mode::JsxText),
+        _       => Mode::JsxTag
+    };
+    (ParseState::new(mode, nesting), ParseAction::Nothing)
+}
+
+pub fn state_transition(syntax: Syntax, from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    let nesting = from.nesting;
+    let state = |mode| (ParseState::new(mode, nesting.clone()), ParseAction::Nothing);
+    match current_char {
+        Some(c) => match from.mode {
+            Mode::Start => match c {
+                '#'     => state(Mode::Shebang),
+                _       => normal(syntax, Token::Operator, nesting, c)
+            },
+            Mode::Shebang => match c {
+                '\n'    => state(Mode::Normal(Token::Operator)),
+                _       => state(Mode::Shebang)
+            },
+            Mode::Normal(token) =>
+                normal(syntax, token, nesting, c),
+            Mode::Word(word) if is_identifier(c) =>
+                state(Mode::Word(word.push(c))),
+            Mode::Word(word) => {
+                let token = if word.is_keyword() { Token::Operator } else { Token::Operand };
+                normal(syntax, token, nesting, c)
+            },
+            Mode::Property if is_identifier(c) =>
+                state(Mode::Property),
+            Mode::Property =>
+                normal(syntax, Token::Operand, nesting, c),
+            Mode::FirstSlash(token) => match c {
+                '/'     => (ParseState::new(Mode::SecondSlash(token), nesting), ParseAction::CommentConfirmed(CommentKind::Line)),
+                '*'     => (ParseState::new(Mode::BlockCommentStart(token), nesting), ParseAction::CommentConfirmed(CommentKind::Block)),
+                _ if token == Token::Operand => match normal(syntax, Token::Operator, nesting, c) {
+                    (state, ParseAction::Nothing) => (state, ParseAction::CommentDismissed),
+                    other => other
+                },
+                _       => (regex(nesting, c), ParseAction::CommentDismissed)
+            },
+            Mode::SecondSlash(token) => match c {
+                '/'     => (ParseState::new(Mode::ThirdSlash(token), nesting), ParseAction::CommentKindChanged(CommentKind::Doc)),
+                '\n'    => (ParseState::new(Mode::Normal(token), nesting), ParseAction::CommentEnds),
+                _       => state(Mode::LineComment(token))
+            },
+            Mode::ThirdSlash(token) => match c {
+                '/'     => (ParseState::new(Mode::LineComment(token), nesting), ParseAction::CommentKindChanged(CommentKind::Line)),
+                '\n'    => (ParseState::new(Mode::Normal(token), nesting), ParseAction::CommentEnds),
+                _       => state(Mode::LineComment(token))
+            },
+            Mode::LineComment(token) => match c {
+                '\n'    => (ParseState::new(Mode::Normal(token), nesting), ParseAction::CommentEnds),
+                _       => state(Mode::LineComment(token))
+            },
+            Mode::BlockCommentStart(token) => match c {
+                '*'     => (ParseState::new(Mode::BlockDocStart(token), nesting), ParseAction::Nothing),
+                _       => state(Mode::BlockComment(token))
+            },
+            Mode::BlockDocStart(token) => match c {
+                '/'     => (ParseState::new(Mode::Normal(token), nesting), ParseAction::BlockCommentEnds),
+                '*'     => state(Mode::BlockCommentStar(token)),
+                _       => (ParseState::new(Mode::BlockComment(token), nesting), ParseAction::CommentKindChanged(CommentKind::Doc))
+            },
+            Mode::BlockComment(token) => match c {
+                '*'     => state(Mode::BlockCommentStar(token)),
+                _       => state(Mode::BlockComment(token))
+            },
+            Mode::BlockCommentStar(token) => match c {
+                '/'     => (ParseState::new(Mode::Normal(token), nesting), ParseAction::BlockCommentEnds),
+                '*'     => state(Mode::BlockCommentStar(token)),
+                _       => state(Mode::BlockComment(token))
+            },
+            Mode::String(quote) => match c {
+                '\\'    => state(Mode::StringEscaped(quote)),
+                c if c == quote
+                        => state(Mode::Normal(Token::Operand)),
+                _       => state(Mode::String(quote))
+            },
+            Mode::StringEscaped(quote) =>
+                state(Mode::String(quote)),
+            Mode::Template | Mode::TemplateDollar => match c {
+                '{' if from.mode == Mode::TemplateDollar
+                        => open(nesting, Frame::Substitution, Mode::Normal(Token::Operator)),
+                '`'     => state(Mode::Normal(Token::Operand)),
+                '\\'    => state(Mode::TemplateEscaped),
+                '$'     => state(Mode::TemplateDollar),
+                _       => state(Mode::Template)
+            },
+            Mode::TemplateEscaped =>
+                state(Mode::Template),
+            Mode::Regex =>
+                (regex(nesting, c), ParseAction::Nothing),
+            Mode::RegexEscaped =>
+                state(Mode::Regex),
+            Mode::RegexClass => match c {
+                ']'     => state(Mode::Regex),
+                '\\'    => state(Mode::RegexClassEscaped),
+                _       => state(Mode::RegexClass)
+            },
+            Mode::RegexClassEscaped =>
+                state(Mode::RegexClass),
+            Mode::JsxOpen => match c {
+                '>'     => open(nesting, Frame::Element, Mode::JsxText),
+                c if (c.is_alphabetic() || c == '_' || c == '$') && syntax == Syntax::Tsx
+                        => state(Mode::JsxName),
+                c if c.is_alphabetic() || c == '_' || c == '$'
+                        => jsx_tag(nesting, c),
+                _       => normal(syntax, Token::Operator, nesting, c)
+            },
+            Mode::JsxName => match c {
+                ',' | '='
+                        => state(Mode::Normal(Token::Operator)),
+                '.' | '-' | ':'
+                        => state(Mode::JsxName),
+                c if is_identifier(c)
+                        => state(Mode::JsxName),
+                c if c.is_whitespace()
+                        => state(Mode::JsxNameSpace(Word::empty())),
+                _       => jsx_tag(nesting, c)
+            },
+            Mode::JsxNameSpace(word) => match c {
+                ',' | '=' if word.len == 0
+                        => state(Mode::Normal(Token::Operator)),
+                c if is_identifier(c)
+                        => state(Mode::JsxNameSpace(word.push(c))),
+                c if c.is_whitespace() && word.len == 0
+                        => state(Mode::JsxNameSpace(word)),
+                c if c.is_whitespace() && word.is("extends")
+                        => state(Mode::Normal(Token::Operator)),
+                _       => jsx_tag(nesting, c)
+            },
+            Mode::JsxTag =>
+                jsx_tag(nesting, c),
+            Mode::JsxTagSlash => match c {
+                '>'     => (after_element(nesting), ParseAction::Nothing),
+                _       => jsx_tag(nesting, c)
+            },
+            Mode::JsxAttribute(quote) if c == quote =>
+                state(Mode::JsxTag),
+            Mode::JsxAttribute(quote) =>
+                state(Mode::JsxAttribute(quote)),
+            Mode::JsxText => match c {
+                '<'     => state(Mode::JsxTextOpen),
+                '{'     => open(nesting, Frame::ChildExpression, Mode::Normal(Token::Operator)),
+                _       => state(Mode::JsxText)
+            },
+            Mode::JsxTextOpen => match c {
+                '/'     => state(Mode::JsxClosingTag),
+                '>'     => open(nesting, Frame::Element, Mode::JsxText),
+                _       => jsx_tag(nesting, c)
+            },
+            Mode::JsxClosingTag => match c {
+                '>'     => (after_element(nesting.pop()), ParseAction::Nothing),
+                _       => state(Mode::JsxClosingTag)
+            },
+            Mode::End =>
+                (ParseState::end(), ParseAction::Nothing)
+        },
+        None => match from.mode {
+            Mode::FirstSlash(_) => (ParseState::end(), ParseAction::CommentDismissed),
+            Mode::SecondSlash(_) | Mode::ThirdSlash(_) | Mode::LineComment(_) => (ParseState::end(), ParseAction::CommentEnds),
+            Mode::BlockCommentStart(_) | Mode::BlockDocStart(_) | Mode::BlockComment(_) | Mode::BlockCommentStar(_)
+                => (ParseState::end(), ParseAction::CommentUnterminated),
+            _ => (ParseState::end(), ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum CommentState {
+    NotInComment,
+    MaybeInComment(usize),
+    InComment(usize, CommentKind)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from, _) => Some(from)
+        }
+    }
+}
+
+pub fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentMightStart => {
+            comment_state = CommentState::MaybeInComment(position);
+        },
+        ParseAction::CommentConfirmed(kind) => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    comment_state = CommentState::InComment(from, kind);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("javascript style"), position));
+                }
+            }
+        },
+        ParseAction::CommentKindChanged(kind) => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    comment_state = CommentState::InComment(from, kind);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("javascript style"), position));
+                }
+            }
+        },
+        ParseAction::CommentDismissed => {
+            comment_state = CommentState::NotInComment;
+        },
+        ParseAction::CommentEnds | ParseAction::BlockCommentEnds => {
+            let to = if action == ParseAction::BlockCommentEnds { position + 1 } else { position };
+            match comment_state {
+                CommentState::InComment(from, kind) => {
+                    matches.push(CommentMatch { from, to, kind });
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("javascript style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("javascript style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+/// Comments kept by minifiers, like `/*! ... */` or the ones containing `@license` or `@preserve`
+pub fn license_comments() -> Regex {
+    Regex::new(r"^/\*(!|[\s\S]*@(license|preserve)\b)").expect("license comment pattern is a valid regex")
+}
+
+pub fn find_comments(input: &str, syntax: Syntax) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, move |s, c| state_transition(syntax, s, c), do_action)
+}
+
+pub fn scanner(syntax: Syntax) -> Box<dyn Scan> {
+    Box::new(Scanner::new(move |s, c| state_transition(syntax, s, c), do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::comment_texts;
+
+    #[test]
+    fn line_and_block_comments() {
+        let input = "let a = 1; // one\n/* two */ let b = '//' + \"/*\";";
+        let expected = Ok(vec![
+            CommentMatch { from: 11, to: 17, kind: CommentKind::Line },
+            CommentMatch { from: 18, to: 27, kind: CommentKind::Block }
+        ]);
+        assert_eq!(expected, find_comments(input, Syntax::Jsx));
+    }
+
+    #[test]
+    fn doc_comments() {
+        let input = "/** doc */ let a; /// doc\n//// line\n/**/ /*** block */ x = a /** doc */ / 2;";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 10, kind: CommentKind::Doc },
+            CommentMatch { from: 18, to: 25, kind: CommentKind::Doc },
+            CommentMatch { from: 26, to: 35, kind: CommentKind::Line },
+            CommentMatch { from: 36, to: 40, kind: CommentKind::Block },
+            CommentMatch { from: 41, to: 54, kind: CommentKind::Block },
+            CommentMatch { from: 61, to: 71, kind: CommentKind::Doc }
+        ]);
+        assert_eq!(expected, find_comments(input, Syntax::Plain));
+    }
+
+    #[test]
+    fn regex_or_division() {
+        let input = "x = /\\/\\*/.test(s) /* a */; y = a / b / c; // b\nz = [/[/*]/g, i++ / 2 // c\n];";
+        let expected = vec![
+            ("/* a */", CommentKind::Block),
+            ("// b", CommentKind::Line),
+            ("// c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+        let input = "return /'/.test(s) // a\nif (typeof /\"/ === t) {} // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+        let input = "n = width / 2 /* a */ / 4; m = obj.return / 2 // b";
+        let expected = vec![
+            ("/* a */", CommentKind::Block),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+    }
+
+    #[test]
+    fn template_literals() {
+        let input = "`// ${ a /* a */ + `${ {b: '//'}.b }//` } /* `; // b";
+        let expected = vec![
+            ("/* a */", CommentKind::Block),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+        let input = "`$` // a\n`\\${ // }` // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+    }
+
+    #[test]
+    fn jsx_text() {
+        let input = "const a = <p title=\"//\">it's // text {/* a */}<b>/* x */</b></p>; // b\n\
+                     const f = <>{c ? <i/> : 'x'}</> /* c */;";
+        let expected = vec![
+            ("/* a */", CommentKind::Block),
+            ("// b", CommentKind::Line),
+            ("/* c */", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+        let input = "if (a < b) { c = d<e>(f) } // a";
+        let expected = vec![
+            ("// a", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+    }
+
+    #[test]
+    fn typescript_type_assertion() {
+        let input = "let a = <T>b; // a\nconst f = <T,>(x: T) => x; // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Plain)));
+    }
+
+    #[test]
+    fn non_null_assertion() {
+        let input = "x = obj!/2 // a\ny = f()! / a[0]! / 2; // b\nif (!/re/.test(s)) {} // c";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line),
+            ("// c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Plain)));
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Tsx)));
+    }
+
+    #[test]
+    fn tsx_type_parameters() {
+        let input = "const f = <T,>(x: T) => x;\nconst url = \"http://x\"; // c";
+        let expected = vec![
+            ("// c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Tsx)));
+        let input = "const g = <T extends Record<string, U> = {}>(x: T) => <p>it's // text</p>; // a\n\
+                     const h = <T = string,>() => 1; // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Tsx)));
+        let input = "const e = <T extends>don't // text</T>; // a\nconst i = <a.b c=\"//\" d>it's</a.b>; // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Tsx)));
+    }
+
+    #[test]
+    fn shebang() {
+        let input = "#!/usr/bin/env node //x\nlet a; // a";
+        let expected = vec![
+            ("// a", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, Syntax::Jsx)));
+    }
+
+    #[test]
+    fn license_comment_pattern() {
+        let pattern = license_comments();
+        assert!(pattern.is_match("/*! v1.0 */"));
+        assert!(pattern.is_match("/**\n * @license MIT\n */"));
+        assert!(pattern.is_match("/* @preserve */"));
+        assert!(!pattern.is_match("/* a license */"));
+        assert!(!pattern.is_match("// @license"));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 1000;
+        let input = "`${".repeat(depth) + " // a\n" + &"}`".repeat(depth) + " // b";
+        let expected = vec![
+            ("// a", CommentKind::Line),
+            ("// b", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(&input, find_comments(&input, Syntax::Jsx)));
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let input = "let a;\n/* comment";
+        let expected = Err(ScanError::new(ErrorKind::UnterminatedComment, 7).locate(input));
+        assert_eq!(expected, find_comments(input, Syntax::Jsx));
+    }
+}
@@ -0,0 +1,118 @@
+extern crate serde;
+extern crate serde_json;
+
+use self::serde::de::IgnoredAny;
+use super::{CommentMatch, Error, ErrorKind};
+
+/// Commas that only have whitespace and comments before the next `]` or `}`.
+/// `comments` must be sorted.
+fn trailing_commas(input: &str, comments: &[CommentMatch]) -> Vec<(usize, usize)> {
+    let mut commas = Vec::new();
+    let mut comments = comments.iter().peekable();
+    let mut pending = None;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if let Some(m) = comments.peek().filter(|m| m.from == i).cloned() {
+            comments.next();
+            /* skip the rest of the comment */
+            while chars.clone().next().is_some_and(|(j, _)| j < m.to) {
+                chars.next();
+            }
+            continue;
+        }
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => {},
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                pending = None;
+            },
+            (None, ',') => pending = Some(i),
+            (None, ']') | (None, '}') => {
+                commas.extend(pending.take().map(|from| (from, from + 1)));
+            },
+            (None, c) if c.is_whitespace() => {},
+            (None, _) => pending = None
+        }
+    }
+    commas
+}
+
+/// Offset in the input of an offset in the output, skipping the removed ranges
+fn original_offset(offset: usize, removed: &[(usize, usize)]) -> usize {
+    removed.iter()
+        .fold(offset, |offset, &(from, to)| if from <= offset { offset + to - from } else { offset })
+}
+
+/// Byte offset of a 1-based line and column as reported by serde_json
+fn offset_of(text: &str, line: usize, column: usize) -> usize {
+    let line_start = text.split('\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum::<usize>();
+    (line_start + column.saturating_sub(1)).min(text.len())
+}
+
+/// Remove the comments and optionally trailing commas, and make sure what is left is strict JSON.
+/// Errors are located in the input, not in the output.
+pub fn strict(input: &str, comments: &[CommentMatch], remove_trailing_commas: bool) -> Result<String, Error> {
+    let mut comments = comments.to_vec();
+    comments.sort_by_key(|m| m.from);
+    let mut removed: Vec<(usize, usize)> = comments.iter().map(|m| (m.from, m.to)).collect();
+    if remove_trailing_commas {
+        removed.extend(trailing_commas(input, &comments));
+        removed.sort();
+    }
+    let mut output = String::with_capacity(input.len());
+    let mut position = 0;
+    for &(from, to) in &removed {
+        output.push_str(&input[position..from]);
+        position = to;
+    }
+    output.push_str(&input[position..]);
+    if let Err(e) = serde_json::from_str::<IgnoredAny>(&output) {
+        let offset = original_offset(offset_of(&output, e.line(), e.column()), &removed);
+        let suffix = format!(" at line {} column {}", e.line(), e.column());
+        let message = e.to_string().trim_end_matches(&suffix[..]).to_owned();
+        return Err(Error::new(ErrorKind::InvalidJson(message), input, offset));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{find_comments, CommentStyle, Location};
+
+    fn strict_json(input: &str, remove_trailing_commas: bool) -> Result<String, Error> {
+        let comments = find_comments(input, &CommentStyle::Json).unwrap();
+        strict(input, &comments, remove_trailing_commas)
+    }
+
+    #[test]
+    fn strips_comments() {
+        let input = "{\n  // line\n  \"a\": \"// not\", /* block */ \"b\": [1, 2]\n}\n";
+        assert_eq!(Ok("{\n  \n  \"a\": \"// not\",  \"b\": [1, 2]\n}\n".to_owned()), strict_json(input, false));
+    }
+
+    #[test]
+    fn removes_trailing_commas() {
+        let input = "{\"a\": [1, 2,], \"b\": \",]\", // x\n}";
+        assert_eq!(Ok("{\"a\": [1, 2], \"b\": \",]\" \n}".to_owned()), strict_json(input, true));
+    }
+
+    #[test]
+    fn locates_errors_in_the_input() {
+        let input = "{\n  /* ä comment */ \"a\": 1,\n}";
+        let error = strict_json(input, false).unwrap_err();
+        assert_eq!(Location { offset: 29, line: 3, column: 1 }, error.location);
+        assert_eq!(ErrorKind::InvalidJson("key must be a string".to_owned()), error.kind);
+        let input = "{ /* x */ unquoted: 1 }";
+        let error = strict_json(input, true).unwrap_err();
+        assert_eq!(10, error.location.offset);
+    }
+}
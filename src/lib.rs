@@ -1,39 +1,250 @@
-#[macro_use] 
+#[macro_use]
 extern crate quick_error;
+extern crate regex;
+#[macro_use]
+extern crate serde_derive;
 
 mod c;
+mod css;
+mod custom;
+mod html;
+mod ini;
+mod js;
+mod json;
+mod properties;
+mod python;
+mod rust;
 mod shell;
+mod sql;
+mod toml;
+mod yaml;
 mod xml;
 mod blanklines;
+mod detect;
+mod error;
+mod filter;
+mod stream;
 
-use std::io;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::Path;
+use regex::Regex;
 
-quick_error! {
-    #[derive(Debug)]
-    pub enum AppError {
-        Io(err: io::Error) {
-            from()
-            cause(err)
-            description(err.description())
+pub use detect::Detector;
+pub use custom::StyleSpec;
+pub use sql::SqlDialect;
+pub use error::{AppError, Error, ErrorKind, ScanError, SpecError, StreamError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommentStyle {
+    C,
+    XML,
+    /// `<!-- -->` comments and the comments in `<script>` and `<style>`
+    Html,
+    Shell,
+    Rust,
+    Python,
+    /// JavaScript with JSX, regex literals and template strings
+    JavaScript,
+    /// JavaScript without JSX, where `<T>x` is a type assertion
+    TypeScript,
+    /// TypeScript with JSX, where `<T,>(x: T) => x` is a generic arrow function
+    Tsx,
+    Css,
+    /// CSS with `//` line comments
+    Scss,
+    Less,
+    Sql(SqlDialect),
+    Ini,
+    Toml,
+    /// Java `.properties`
+    Properties,
+    Yaml,
+    /// JSON with comments like `tsconfig.json`, stripped to strict JSON
+    Json,
+    /// described by the user, see `StyleSpec`
+    Custom(StyleSpec)
+}
+
+impl CommentStyle {
+    /// Style of a file from its extension, or the shebang and modelines in its first bytes
+    pub fn detect(path: Option<&Path>, first_bytes: &[u8]) -> Option<CommentStyle> {
+        Detector::new().detect(path, first_bytes)
+    }
+
+    /// Name as used on the command line
+    pub fn name(&self) -> &str {
+        match *self {
+            CommentStyle::C => "c",
+            CommentStyle::XML => "xml",
+            CommentStyle::Html => "html",
+            CommentStyle::Shell => "shell",
+            CommentStyle::Rust => "rust",
+            CommentStyle::Python => "python",
+            CommentStyle::JavaScript => "javascript",
+            CommentStyle::TypeScript => "typescript",
+            CommentStyle::Tsx => "tsx",
+            CommentStyle::Css => "css",
+            CommentStyle::Scss => "scss",
+            CommentStyle::Less => "less",
+            CommentStyle::Sql(SqlDialect::Standard) => "sql",
+            CommentStyle::Sql(dialect) => dialect.name(),
+            CommentStyle::Ini => "ini",
+            CommentStyle::Toml => "toml",
+            CommentStyle::Properties => "properties",
+            CommentStyle::Yaml => "yaml",
+            CommentStyle::Json => "jsonc",
+            CommentStyle::Custom(ref spec) => &spec.name
         }
-        Other(s: &'static str) {
-            from()
-            description(s)
+    }
+
+    /// Opening and closing delimiters of the comments, longest first
+    fn delimiters(&self) -> Vec<(&str, &str)> {
+        match *self {
+            CommentStyle::C | CommentStyle::JavaScript | CommentStyle::TypeScript | CommentStyle::Tsx
+                | CommentStyle::Json =>
+                vec![("//", ""), ("/**", "*/"), ("/*", "*/")],
+            CommentStyle::XML => vec![("<!--", "-->")],
+            CommentStyle::Css => vec![("/*", "*/")],
+            CommentStyle::Scss | CommentStyle::Less => vec![("//", ""), ("/*", "*/")],
+            CommentStyle::Html => vec![("<!--", "-->"), ("//", ""), ("/*", "*/")],
+            CommentStyle::Shell | CommentStyle::Python | CommentStyle::Toml | CommentStyle::Yaml =>
+                vec![("#", "")],
+            CommentStyle::Rust => vec![("///", ""), ("//!", ""), ("//", ""),
+                                       ("/**", "*/"), ("/*!", "*/"), ("/*", "*/")],
+            CommentStyle::Sql(SqlDialect::MySql) => vec![("--", ""), ("/*", "*/"), ("#", "")],
+            CommentStyle::Sql(_) => vec![("--", ""), ("/*", "*/")],
+            CommentStyle::Ini => vec![(";", ""), ("#", "")],
+            CommentStyle::Properties => vec![("#", ""), ("!", "")],
+            CommentStyle::Custom(ref spec) => spec.delimiters()
         }
     }
+
+    /// Text of a comment without its delimiters like `//`, `/* */`, `#` or `<!-- -->`
+    /// and the whitespace around it
+    pub fn trim_delimiters<'a>(&self, comment: &'a str) -> &'a str {
+        self.delimiters().iter()
+            .find(|&&(open, close)| {
+                comment.len() >= open.len() + close.len()
+                    && comment.starts_with(open) && comment.ends_with(close)
+            })
+            .map_or(comment, |&(open, close)| &comment[open.len()..comment.len() - close.len()])
+            .trim()
+    }
+
+    pub fn from_name(name: &str) -> Option<CommentStyle> {
+        let builtin = [CommentStyle::C, CommentStyle::XML, CommentStyle::Html, CommentStyle::Shell,
+                       CommentStyle::Rust, CommentStyle::Python, CommentStyle::JavaScript,
+                       CommentStyle::TypeScript, CommentStyle::Tsx, CommentStyle::Css, CommentStyle::Scss, CommentStyle::Less,
+                       CommentStyle::Ini, CommentStyle::Toml, CommentStyle::Properties, CommentStyle::Yaml,
+                       CommentStyle::Json];
+        builtin.iter().cloned()
+            .chain(SqlDialect::ALL.iter().map(|&dialect| CommentStyle::Sql(dialect)))
+            .find(|style| style.name() == name)
+            .or_else(|| StyleSpec::builtin(name).map(CommentStyle::Custom))
+    }
 }
 
+/// What a comment looks like, as told by the scanner that found it
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CommentStyle {
-    C,
-    XML,
-    Shell
+pub enum CommentKind {
+    /// runs until the end of the line like `// ...` or `# ...`
+    Line,
+    /// has a closing delimiter like `/* ... */` or `<!-- ... -->`
+    Block,
+    /// documentation like `/// ...` or a Python docstring
+    Doc,
+    /// trails the shebang like `#!/bin/sh # ...`
+    Shebang,
+    /// an instruction for a tool like `//go:build linux`
+    Directive
+}
+
+impl CommentKind {
+    pub const ALL: [CommentKind; 5] = [CommentKind::Line, CommentKind::Block, CommentKind::Doc,
+                                       CommentKind::Shebang, CommentKind::Directive];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CommentKind::Line => "line",
+            CommentKind::Block => "block",
+            CommentKind::Doc => "doc",
+            CommentKind::Shebang => "shebang",
+            CommentKind::Directive => "directive"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CommentKind> {
+        CommentKind::ALL.iter()
+            .find(|kind| kind.name() == name)
+            .cloned()
+    }
 }
 
+/// Range of a comment in the input, `from` and `to` are byte offsets
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CommentMatch {
     pub from: usize,
-    pub to: usize
+    pub to: usize,
+    pub kind: CommentKind
+}
+
+impl CommentMatch {
+    /// Locations of the first and the past-the-end byte of the match
+    pub fn locate(&self, input: &str) -> (Location, Location) {
+        let from = Location::new(input, self.from);
+        let to = from.advance(&input[self.from..self.to]);
+        (from, to)
+    }
+}
+
+/// Locations of many sorted matches, reading the input only once
+pub fn locate_matches(input: &str, matches: &[CommentMatch]) -> Vec<(Location, Location)> {
+    let mut location = Location::start();
+    matches.iter()
+        .map(|m| {
+            let from = location.advance(&input[location.offset..m.from]);
+            let to = from.advance(&input[m.from..m.to]);
+            location = to;
+            (from, to)
+        })
+        .collect()
+}
+
+/// Position in the input, `line` and `column` are 1-based and
+/// `column` counts chars, not bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Location {
+    pub fn start() -> Self {
+        Location { offset: 0, line: 1, column: 1 }
+    }
+
+    /// `offset` must lie on a char boundary of `input`
+    pub fn new(input: &str, offset: usize) -> Self {
+        Location::start().advance(&input[..offset])
+    }
+
+    /// Location after reading `text` starting at this location
+    pub fn advance(&self, text: &str) -> Self {
+        let mut location = *self;
+        for c in text.chars() {
+            if c == '\n' {
+                location.line += 1;
+                location.column = 1;
+            } else {
+                location.column += 1;
+            }
+        }
+        location.offset += text.len();
+        location
+    }
 }
 
 pub trait Start {
@@ -44,74 +255,389 @@ pub trait End {
     fn end() -> Self;
 }
 
-pub fn find_comments_impl<P, A, C, FT, FA>(input: &str, state_transition: FT, do_action: FA) 
-    -> Result<Vec<CommentMatch>, &'static str> 
-    where   P: Start + End + Copy + Eq,
-            A: Copy + Eq,
+/// Implemented by comment states that can tell where an unfinished match began
+pub trait Pending {
+    fn pending(&self) -> Option<usize>;
+}
+
+/// State machine driver that is fed one char at a time
+pub struct Scanner<P, A, C, FT, FA> {
+    parse_state: P,
+    comment_state: C,
+    matches: Vec<CommentMatch>,
+    state_transition: FT,
+    do_action: FA,
+    action: PhantomData<A>
+}
+
+impl<P, A, C, FT, FA> Scanner<P, A, C, FT, FA>
+    where   P: Start + End + Eq,
+            A: Eq,
             C: Start + Copy + Eq,
             FT: Fn(P, Option<char>) -> (P, A),
             FA: Fn(A, C, usize, Vec<CommentMatch>) 
-                -> Result<(C, Vec<CommentMatch>), &'static str> {
-    let mut matches = Vec::new();
-    let mut current_parse_state = P::start();
-    let mut current_comment_state = C::start();
-    let mut chars = input.chars();
-    let mut position = 0;
-    while current_parse_state != P::end() {
-        let current_char = chars.next();
+                -> Result<(C, Vec<CommentMatch>), ScanError> {
+    pub fn new(state_transition: FT, do_action: FA) -> Self {
+        Scanner {
+            parse_state: P::start(),
+            comment_state: C::start(),
+            matches: Vec::new(),
+            state_transition,
+            do_action,
+            action: PhantomData
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.parse_state == P::end()
+    }
+
+    /// Feed the char at byte `position`, `None` marks the end of the input
+    pub fn step(&mut self, current_char: Option<char>, position: usize) -> Result<(), ScanError> {
+        let parse_state = mem::replace(&mut self.parse_state, P::end());
         let (next_parse_state, action) = 
-            state_transition(current_parse_state, current_char);
+            (self.state_transition)(parse_state, current_char);
+        let matches = mem::take(&mut self.matches);
         let (next_comment_state, next_matches) = 
-            do_action(action, current_comment_state, position, matches)?;
-        current_parse_state = next_parse_state;
-        current_comment_state = next_comment_state;
-        matches = next_matches;
-        position += 1;
+            (self.do_action)(action, self.comment_state, position, matches)?;
+        self.parse_state = next_parse_state;
+        self.comment_state = next_comment_state;
+        self.matches = next_matches;
+        Ok(())
+    }
+
+    pub fn take_matches(&mut self) -> Vec<CommentMatch> {
+        mem::take(&mut self.matches)
     }
-    Ok(matches)
 }
 
-fn find_comments(input: &str, style: &CommentStyle) -> Result<Vec<CommentMatch>, &'static str> {
-    match style {
-        &CommentStyle::C => c::find_comments(input),
-        &CommentStyle::Shell => shell::find_comments(input),
-        &CommentStyle::XML => xml::find_comments(input)
+/// Object safe interface of a `Scanner`, used for streaming
+pub trait Scan {
+    fn is_finished(&self) -> bool;
+    fn step(&mut self, current_char: Option<char>, position: usize) -> Result<(), ScanError>;
+    fn take_matches(&mut self) -> Vec<CommentMatch>;
+    /// Start of a match that might still be in progress
+    fn pending(&self) -> Option<usize>;
+}
+
+impl<P, A, C, FT, FA> Scan for Scanner<P, A, C, FT, FA>
+    where   P: Start + End + Eq,
+            A: Eq,
+            C: Start + Pending + Copy + Eq,
+            FT: Fn(P, Option<char>) -> (P, A),
+            FA: Fn(A, C, usize, Vec<CommentMatch>) 
+                -> Result<(C, Vec<CommentMatch>), ScanError> {
+    fn is_finished(&self) -> bool {
+        Scanner::is_finished(self)
+    }
+
+    fn step(&mut self, current_char: Option<char>, position: usize) -> Result<(), ScanError> {
+        Scanner::step(self, current_char, position)
+    }
+
+    fn take_matches(&mut self) -> Vec<CommentMatch> {
+        Scanner::take_matches(self)
+    }
+
+    fn pending(&self) -> Option<usize> {
+        self.comment_state.pending()
     }
 }
 
-fn remove_matches(input: String, matches: Vec<CommentMatch>) -> Result<String, &'static str> {
-    let mut input = input;
+pub fn find_comments_impl<P, A, C, FT, FA>(input: &str, state_transition: FT, do_action: FA) 
+    -> Result<Vec<CommentMatch>, Error> 
+    where   P: Start + End + Eq,
+            A: Eq,
+            C: Start + Copy + Eq,
+            FT: Fn(P, Option<char>) -> (P, A),
+            FA: Fn(A, C, usize, Vec<CommentMatch>) 
+                -> Result<(C, Vec<CommentMatch>), ScanError> {
+    let mut scanner = Scanner::new(state_transition, do_action);
+    let mut chars = input.char_indices();
+    while !scanner.is_finished() {
+        let (position, current_char) = match chars.next() {
+            Some((position, c)) => (position, Some(c)),
+            None => (input.len(), None)
+        };
+        scanner.step(current_char, position)
+            .map_err(|e| e.locate(input))?;
+    }
+    Ok(scanner.take_matches())
+}
+
+/// Find all comments of the given style in the input, directives included.
+/// Unlike `Stripper::find_comments` nothing is left out, directives are only classified.
+pub fn find_comments(input: &str, style: &CommentStyle) -> Result<Vec<CommentMatch>, Error> {
+    Stripper::new(style.clone()).keep_directives(false).find_comments(input)
+}
+
+/// Copy the input leaving out all matched ranges
+pub fn remove_matches(input: &str, matches: Vec<CommentMatch>) -> Result<String, Error> {
     let mut matches = matches;
     matches.sort_by_key(|m| m.from);
-    /* must come before reversing */
-    check_sorted_matches(input.as_str(), &matches)?;
-    matches.reverse();
+    check_sorted_matches(input, &matches)?;
+    let mut output = String::with_capacity(input.len());
+    let mut position = 0;
     for m in matches {
-        input.drain((m.from)..(m.to));
+        output.push_str(&input[position..m.from]);
+        position = m.to;
     }
-    Ok(input.to_owned())
+    output.push_str(&input[position..]);
+    Ok(output)
 }
 
-fn check_sorted_matches(input: &str, matches: &Vec<CommentMatch>) -> Result<(), &'static str> {
-    if matches.iter().any(|m| m.from >= input.len() || m.to > input.len()) {
-        return Err("match out of range");
+fn check_sorted_matches(input: &str, matches: &[CommentMatch]) -> Result<(), Error> {
+    if let Some(m) = matches.iter().find(|m| m.from >= input.len() || m.to > input.len() || m.from > m.to
+            || !input.is_char_boundary(m.from) || !input.is_char_boundary(m.to)) {
+        return Err(Error::new(ErrorKind::MatchOutOfRange, input, m.from));
     }
-    if matches.iter().zip(matches.iter().skip(1)).any(|(m, n)| m.to > n.from) {
-        return Err("matches overlapping");
+    if let Some((_, n)) = matches.iter().zip(matches.iter().skip(1)).find(|&(m, n)| m.to > n.from) {
+        return Err(Error::new(ErrorKind::OverlappingMatches, input, n.from));
     }
     Ok(())
 }
 
-pub fn strip_comments(data: String, style: CommentStyle, remove_blanks: bool) -> Result<String, &'static str> {
-    let comment_matches = find_comments(data.as_str(), &style)?;
-    let mut stripped = remove_matches(data, comment_matches)?;
-    if remove_blanks {
-        let blank_matches = blanklines::find_blanklines(stripped.as_str())?;
-        stripped = remove_matches(stripped, blank_matches)?;
+/// Decode the input, reporting the location of the first invalid byte
+pub fn from_utf8(data: Vec<u8>) -> Result<String, Error> {
+    String::from_utf8(data).map_err(|e| {
+        let valid_up_to = e.utf8_error().valid_up_to();
+        let valid = String::from_utf8_lossy(&e.as_bytes()[..valid_up_to]);
+        Error::new(ErrorKind::InvalidUtf8, &valid, valid_up_to)
+    })
+}
+
+/// Strips comments of one style, configure once and reuse for many inputs
+#[derive(Debug, Clone)]
+pub struct Stripper {
+    style: CommentStyle,
+    remove_blanks: bool,
+    keep_doc_comments: bool,
+    strip_docstrings: bool,
+    remove_trailing_commas: bool,
+    keep_license_comments: bool,
+    keep_kinds: Vec<CommentKind>,
+    keep_header: bool,
+    keep_matching: Vec<Regex>,
+    keep_directives: bool,
+    directives: Vec<Regex>
+}
+
+impl Stripper {
+    /// Stripper for the given style that also removes multiple blank lines
+    /// and keeps the built-in directives of the style. So by default neither `strip`
+    /// nor `find_comments` touch comments like `// eslint-disable` or `# noqa`,
+    /// unless `keep_directives(false)` is set.
+    pub fn new(style: CommentStyle) -> Self {
+        Stripper {
+            directives: filter::directives(&style),
+            style,
+            remove_blanks: true,
+            keep_doc_comments: false,
+            strip_docstrings: false,
+            remove_trailing_commas: false,
+            keep_license_comments: false,
+            keep_kinds: Vec::new(),
+            keep_header: false,
+            keep_matching: Vec::new(),
+            keep_directives: true
+        }
+    }
+
+    /// Whether to collapse multiple blank lines left behind after stripping
+    pub fn remove_blanks(mut self, remove_blanks: bool) -> Self {
+        self.remove_blanks = remove_blanks;
+        self
+    }
+
+    /// Whether to keep doc comments like `///` or `/** */`, only affects the Rust style
+    pub fn keep_doc_comments(mut self, keep_doc_comments: bool) -> Self {
+        self.keep_doc_comments = keep_doc_comments;
+        self
+    }
+
+    /// Whether to empty docstrings, only affects the Python style.
+    /// The quotes are kept, so a body consisting of just a docstring stays valid.
+    pub fn strip_docstrings(mut self, strip_docstrings: bool) -> Self {
+        self.strip_docstrings = strip_docstrings;
+        self
+    }
+
+    /// Whether to remove commas before `]` and `}`, only affects the JSONC style
+    pub fn remove_trailing_commas(mut self, remove_trailing_commas: bool) -> Self {
+        self.remove_trailing_commas = remove_trailing_commas;
+        self
+    }
+
+    /// Whether to keep `/*! */` and JavaScript comments with `@license` or `@preserve` like minifiers do,
+    /// only affects the JavaScript, TypeScript, TSX, CSS, SCSS and Less styles
+    pub fn keep_license_comments(mut self, keep_license_comments: bool) -> Self {
+        self.keep_license_comments = keep_license_comments;
+        self
+    }
+
+    /// Comments of these kinds are never removed
+    pub fn keep_kinds(mut self, kinds: &[CommentKind]) -> Self {
+        self.keep_kinds = kinds.to_vec();
+        self
+    }
+
+    /// Whether to keep the first comment block, e.g. a license header. It may only follow
+    /// whitespace, a shebang, a byte order mark, an XML declaration or a Python encoding line,
+    /// and ends at a blank line or code.
+    pub fn keep_header(mut self, keep_header: bool) -> Self {
+        self.keep_header = keep_header;
+        self
+    }
+
+    /// Keep comments matching the pattern, the delimiters are part of the matched text
+    pub fn keep_matching(mut self, pattern: Regex) -> Self {
+        self.keep_matching.push(pattern);
+        self
+    }
+
+    /// Whether to keep directives like `// @ts-ignore`, `# noqa` or `//go:build`
+    pub fn keep_directives(mut self, keep_directives: bool) -> Self {
+        self.keep_directives = keep_directives;
+        self
+    }
+
+    /// Treat comments matching the pattern as directives, in addition to the built-in ones.
+    /// The pattern sees the comment without its delimiters and surrounding whitespace.
+    pub fn directive(mut self, pattern: Regex) -> Self {
+        self.directives.push(pattern);
+        self
+    }
+
+    pub fn style(&self) -> &CommentStyle {
+        &self.style
+    }
+
+    /// The comments that are removed, classified by kind
+    pub fn find_comments(&self, input: &str) -> Result<Vec<CommentMatch>, Error> {
+        let matches = match self.style {
+            CommentStyle::C | CommentStyle::Json => c::find_comments(input),
+            CommentStyle::Shell => shell::find_comments(input),
+            CommentStyle::XML => xml::find_comments(input),
+            CommentStyle::Html => html::find_comments(input),
+            CommentStyle::Rust => rust::find_comments(input, self.keep_doc_comments),
+            CommentStyle::Python => python::find_comments(input, self.strip_docstrings),
+            CommentStyle::JavaScript => js::find_comments(input, js::Syntax::Jsx),
+            CommentStyle::TypeScript => js::find_comments(input, js::Syntax::Plain),
+            CommentStyle::Tsx => js::find_comments(input, js::Syntax::Tsx),
+            CommentStyle::Css => css::find_comments(input, false),
+            CommentStyle::Scss | CommentStyle::Less => css::find_comments(input, true),
+            CommentStyle::Sql(dialect) => sql::find_comments(input, dialect),
+            CommentStyle::Ini => ini::find_comments(input),
+            CommentStyle::Toml => toml::find_comments(input),
+            CommentStyle::Properties => properties::find_comments(input),
+            CommentStyle::Yaml => yaml::find_comments(input),
+            CommentStyle::Custom(ref spec) => custom::find_comments(input, spec)
+        }?;
+        Ok(self.filter().apply(input, matches))
+    }
+
+    fn filter(&self) -> filter::Filter {
+        let mut keep_kinds = self.keep_kinds.clone();
+        if self.keep_directives {
+            keep_kinds.push(CommentKind::Directive);
+        }
+        let mut keep_matching = self.keep_matching.clone();
+        match self.style {
+            CommentStyle::JavaScript | CommentStyle::TypeScript | CommentStyle::Tsx if self.keep_license_comments =>
+                keep_matching.push(js::license_comments()),
+            CommentStyle::Css | CommentStyle::Scss | CommentStyle::Less if self.keep_license_comments =>
+                keep_matching.push(css::loud_comments()),
+            _ => {}
+        }
+        filter::Filter::new(self.style.clone(), &self.directives, &keep_kinds, self.keep_header, &keep_matching)
+    }
+
+    fn scanner(&self) -> Box<dyn Scan> {
+        match self.style {
+            CommentStyle::C | CommentStyle::Json => c::scanner(),
+            CommentStyle::Shell => shell::scanner(),
+            CommentStyle::XML => xml::scanner(),
+            CommentStyle::Html => html::scanner(),
+            CommentStyle::Rust => rust::scanner(self.keep_doc_comments),
+            CommentStyle::Python => python::scanner(self.strip_docstrings),
+            CommentStyle::JavaScript => js::scanner(js::Syntax::Jsx),
+            CommentStyle::TypeScript => js::scanner(js::Syntax::Plain),
+            CommentStyle::Tsx => js::scanner(js::Syntax::Tsx),
+            CommentStyle::Css => css::scanner(false),
+            CommentStyle::Scss | CommentStyle::Less => css::scanner(true),
+            CommentStyle::Sql(dialect) => sql::scanner(dialect),
+            CommentStyle::Ini => ini::scanner(),
+            CommentStyle::Toml => toml::scanner(),
+            CommentStyle::Properties => properties::scanner(),
+            CommentStyle::Yaml => yaml::scanner(),
+            CommentStyle::Custom(ref spec) => custom::scanner(spec)
+        }
+    }
+
+    /// With the JSONC style the result is checked to be strict JSON
+    pub fn strip(&self, input: &str) -> Result<String, Error> {
+        let comment_matches = self.find_comments(input)?;
+        let mut stripped = match self.style {
+            CommentStyle::Json => json::strict(input, &comment_matches, self.remove_trailing_commas)?,
+            _ => remove_matches(input, comment_matches)?
+        };
+        if self.remove_blanks {
+            let blank_matches = blanklines::find_blanklines(stripped.as_str())?;
+            stripped = remove_matches(stripped.as_str(), blank_matches)?;
+        }
+        Ok(stripped)
+    }
+
+    /// Only the comments, each followed by a line break
+    pub fn extract(&self, input: &str, trim_delimiters: bool) -> Result<String, Error> {
+        let mut extracted = String::new();
+        for m in self.find_comments(input)? {
+            let comment = &input[m.from..m.to];
+            if trim_delimiters {
+                extracted.push_str(self.style.trim_delimiters(comment));
+            } else {
+                extracted.push_str(comment);
+            }
+            extracted.push('\n');
+        }
+        Ok(extracted)
+    }
+
+    /// Strip while reading, only the longest comment is held in memory at once.
+    /// JSONC is checked as a whole, so it is read completely first.
+    pub fn strip_stream<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<(), StreamError> {
+        if self.style == CommentStyle::Json {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).map_err(StreamError::Read)?;
+            let stripped = self.strip(&from_utf8(data)?)?;
+            return writer.write_all(stripped.as_bytes()).map_err(StreamError::Write);
+        }
+        let mut stages = vec![stream::Stage::new(self.scanner(), Some(self.filter()))];
+        if self.remove_blanks {
+            stages.push(stream::Stage::new(blanklines::scanner(), None));
+        }
+        stream::strip(stages, reader, writer)
     }
-    Ok(stripped)
 }
 
+pub fn strip_comments(data: String, style: CommentStyle, remove_blanks: bool) -> Result<String, Error> {
+    Stripper::new(style)
+        .remove_blanks(remove_blanks)
+        .strip(data.as_str())
+}
+
+/// The inverse of `strip_comments`, keeps only the comments
+pub fn extract_comments(data: &str, style: CommentStyle, trim_delimiters: bool) -> Result<String, Error> {
+    Stripper::new(style).extract(data, trim_delimiters)
+}
+
+/// Text and kind of each comment found, for the tests of the styles
+#[cfg(test)]
+fn comment_texts(input: &str, matches: Result<Vec<CommentMatch>, Error>) -> Vec<(&str, CommentKind)> {
+    matches.unwrap().iter()
+        .map(|m| (&input[m.from..m.to], m.kind))
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -121,10 +647,10 @@ mod tests {
     fn removes_correctly() {
         let s = "012345#789\n#abcd\nefghi#jkl\n".to_owned();
         let matches = vec![
-            CommentMatch{from:6, to:10},
-            CommentMatch{from:11, to:16},
-            CommentMatch{from:22, to:26}];
-        let stripped = remove_matches(s, matches);
+            CommentMatch { from: 6, to: 10, kind: CommentKind::Line },
+            CommentMatch { from: 11, to: 16, kind: CommentKind::Line },
+            CommentMatch { from: 22, to: 26, kind: CommentKind::Line }];
+        let stripped = remove_matches(s.as_str(), matches);
         assert_eq!(Ok("012345\n\nefghi\n".to_owned()), stripped);
     }
 
@@ -132,11 +658,11 @@ mod tests {
     fn remove_finds_overlapping() {
         let s = "1234567890".to_owned();
         let matches = vec![
-            CommentMatch{from:0, to:5},
-            CommentMatch{from:3, to:7}];
+            CommentMatch { from: 0, to: 5, kind: CommentKind::Line },
+            CommentMatch { from: 3, to: 7, kind: CommentKind::Line }];
         let checked = check_sorted_matches(s.as_str(), &matches);
-        assert!(checked.is_err());
-        let stripped = remove_matches(s, matches);
+        assert_eq!(ErrorKind::OverlappingMatches, checked.unwrap_err().kind);
+        let stripped = remove_matches(s.as_str(), matches);
         assert!(stripped.is_err());
     }
 
@@ -144,12 +670,146 @@ mod tests {
     fn remove_finds_out_of_range() {
         let s = "12345".to_owned();
         let matches = vec![
-            CommentMatch{from:3, to:10},
-            CommentMatch{from:11, to:16}];
+            CommentMatch { from: 3, to: 10, kind: CommentKind::Line },
+            CommentMatch { from: 11, to: 16, kind: CommentKind::Line }];
         let checked = check_sorted_matches(s.as_str(), &matches);
         assert!(checked.is_err());
-        let stripped = remove_matches(s, matches);
+        let stripped = remove_matches(s.as_str(), matches);
         assert!(stripped.is_err());
     }
 
+    #[test]
+    fn remove_finds_split_char() {
+        let s = "aä";
+        let matches = vec![CommentMatch { from: 2, to: 3, kind: CommentKind::Line }];
+        assert!(remove_matches(s, matches).is_err());
+    }
+
+    #[test]
+    fn error_has_location() {
+        let s = "a\nbc /* ö";
+        let expected = Error {
+            kind: ErrorKind::UnterminatedComment,
+            location: Location { offset: 5, line: 2, column: 4 }
+        };
+        assert_eq!(Err(expected), find_comments(s, &CommentStyle::C));
+    }
+
+    #[test]
+    fn invalid_utf8_has_location() {
+        let data = b"ok\n\xc3\xa4 \xff".to_vec();
+        let expected = Error {
+            kind: ErrorKind::InvalidUtf8,
+            location: Location { offset: 6, line: 2, column: 3 }
+        };
+        assert_eq!(Err(expected), from_utf8(data));
+        assert_eq!(Ok("ä".to_owned()), from_utf8(b"\xc3\xa4".to_vec()));
+    }
+
+    #[test]
+    fn style_names() {
+        for style in [CommentStyle::C, CommentStyle::XML, CommentStyle::Html, CommentStyle::Shell,
+                      CommentStyle::Rust, CommentStyle::Python, CommentStyle::JavaScript,
+                      CommentStyle::TypeScript, CommentStyle::Tsx, CommentStyle::Css, CommentStyle::Scss, CommentStyle::Less,
+                      CommentStyle::Sql(SqlDialect::Standard),
+                      CommentStyle::Sql(SqlDialect::PostgreSql), CommentStyle::Sql(SqlDialect::MySql),
+                      CommentStyle::Sql(SqlDialect::Sqlite), CommentStyle::Ini, CommentStyle::Toml,
+                      CommentStyle::Properties, CommentStyle::Yaml, CommentStyle::Json].iter() {
+            assert_eq!(Some(style), CommentStyle::from_name(style.name()).as_ref());
+        }
+        assert_eq!(Some("kotlin"), CommentStyle::from_name("kotlin").as_ref().map(CommentStyle::name));
+        assert_eq!(None, CommentStyle::from_name("cobol"));
+    }
+
+    #[test]
+    fn classifies_directives() {
+        let kinds = |input, style| -> Vec<CommentKind> {
+            find_comments(input, &style).unwrap().iter().map(|m| m.kind).collect()
+        };
+        assert_eq!(vec![CommentKind::Directive, CommentKind::Line, CommentKind::Line, CommentKind::Block],
+                   kinds("//go:build linux\n//TODO: fix\n//http://x\n/* see: x */\n", CommentStyle::C));
+        let go = CommentStyle::from_name("go").unwrap();
+        assert_eq!(vec![CommentKind::Directive, CommentKind::Directive, CommentKind::Line],
+                   kinds("//go:generate x\nf() //nolint:errcheck\n//gopher:x\n", go));
+        assert_eq!(vec![CommentKind::Line, CommentKind::Line, CommentKind::Directive],
+                   kinds("#type:x\n#image:nginx\n# yamllint disable\n", CommentStyle::Yaml));
+        assert_eq!(Some(CommentKind::Directive), CommentKind::from_name("directive"));
+    }
+
+    #[test]
+    fn keeps_selected_kinds() {
+        let input = "//go:build x\na; // line\n/* block */ b;\n";
+        let only_block: Vec<_> = CommentKind::ALL.iter().cloned()
+            .filter(|&k| k != CommentKind::Block)
+            .collect();
+        let stripper = Stripper::new(CommentStyle::C).keep_kinds(&only_block);
+        assert_eq!(Ok("//go:build x\na; // line\n b;\n".to_owned()), stripper.strip(input));
+        let stripper = Stripper::new(CommentStyle::C)
+            .keep_kinds(&[CommentKind::Line])
+            .keep_directives(false);
+        assert_eq!(Ok("a; // line\n b;\n".to_owned()), stripper.strip(input));
+        let mut streamed = Vec::new();
+        stripper.strip_stream(input.as_bytes(), &mut streamed).unwrap();
+        assert_eq!(b"a; // line\n b;\n".to_vec(), streamed);
+    }
+
+    #[test]
+    fn trims_delimiters() {
+        assert_eq!("a", CommentStyle::C.trim_delimiters("// a"));
+        assert_eq!("b", CommentStyle::C.trim_delimiters("/* b */"));
+        assert_eq!("c", CommentStyle::XML.trim_delimiters("<!--c-->"));
+        assert_eq!("d", CommentStyle::Shell.trim_delimiters("#d"));
+        assert_eq!("e", CommentStyle::Rust.trim_delimiters("/// e"));
+        assert_eq!("", CommentStyle::Rust.trim_delimiters("/**/"));
+        assert_eq!("not a comment", CommentStyle::XML.trim_delimiters("not a comment"));
+    }
+
+    #[test]
+    fn extracts_comments() {
+        let input = "int a; // x\n/* y\n */ b; /**/\n";
+        assert_eq!("// x\n/* y\n */\n/**/\n", extract_comments(input, CommentStyle::C, false).unwrap());
+        assert_eq!("x\ny\n\n", extract_comments(input, CommentStyle::C, true).unwrap());
+    }
+
+    #[test]
+    fn stripper_is_reusable() {
+        let stripper = Stripper::new(CommentStyle::Shell).remove_blanks(false);
+        assert_eq!(Ok("a \n\n\nb\n".to_owned()), stripper.strip("a # x\n\n\nb\n"));
+        assert_eq!(Ok("c\n".to_owned()), stripper.strip("c#y\n"));
+        assert_eq!(Ok(vec![CommentMatch { from: 1, to: 3, kind: CommentKind::Line }]), stripper.find_comments("c#y\n"));
+    }
+
+    #[test]
+    fn strips_multibyte_input() {
+        let s = "// Grüße\nlet 名前 = \"日本\"; /* 🦀 */ x\n".to_owned();
+        let stripped = strip_comments(s, CommentStyle::C, true);
+        assert_eq!(Ok("let 名前 = \"日本\";  x\n".to_owned()), stripped);
+    }
+
+    #[test]
+    fn locates_multibyte_input() {
+        let s = "ä\nöü # 😀 x";
+        let m = CommentMatch { from: 8, to: 15, kind: CommentKind::Line };
+        let (from, to) = m.locate(s);
+        assert_eq!(Location { offset: 8, line: 2, column: 4 }, from);
+        assert_eq!(Location { offset: 15, line: 2, column: 8 }, to);
+    }
+
+    #[test]
+    fn find_comments_includes_directives() {
+        let s = "a(); // eslint-disable-line\nb(); // c\n";
+        let all = find_comments(s, &CommentStyle::JavaScript).unwrap();
+        assert_eq!(vec![CommentKind::Directive, CommentKind::Line], all.iter().map(|m| m.kind).collect::<Vec<_>>());
+        let stripped = Stripper::new(CommentStyle::JavaScript).find_comments(s).unwrap();
+        assert_eq!(vec![CommentMatch { from: 33, to: 37, kind: CommentKind::Line }], stripped);
+    }
+
+    #[test]
+    fn locates_all_matches() {
+        let s = "a /* b\n c */ d\n// ü\n";
+        let matches = find_comments(s, &CommentStyle::C).unwrap();
+        let expected: Vec<_> = matches.iter().map(|m| m.locate(s)).collect();
+        assert_eq!(expected, locate_matches(s, &matches));
+        assert_eq!(Location { offset: 15, line: 3, column: 1 }, expected[1].0);
+    }
 }
@@ -1,27 +1,345 @@
 #[macro_use]
 extern crate clap;
 extern crate comment_strip;
+#[macro_use]
+extern crate serde_json;
 
 mod config;
+mod files;
+mod replace;
+mod report;
 
-use std::io::{Read, Write, BufReader, BufWriter};
-use clap::App;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write, BufReader, BufWriter};
+use std::process;
+use clap::{App, Arg};
 use comment_strip::*;
-use config::Config;
+use config::{Config, Format, Input, Mode, Output, Target};
+use files::Job;
+
+/// Exit codes, scripts using `--check` rely on them
+const EXIT_CLEAN: i32 = 0;
+const EXIT_COMMENTS: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+/// The selected style, or the one detected from the path and the start of the input
+fn style(config: &Config, job: &Job, first_bytes: &[u8]) -> Result<CommentStyle, AppError> {
+    let style = match config.style {
+        Some(ref style) => style.clone(),
+        None => config.detector.detect(job.input.as_deref(), first_bytes)
+            .ok_or_else(|| AppError::UnknownStyle(job.input_name()))?
+    };
+    Ok(match (style, config.sql_dialect) {
+        (CommentStyle::Sql(_), Some(dialect)) => CommentStyle::Sql(dialect),
+        (style, _) => style
+    })
+}
+
+fn stripper(config: &Config, style: CommentStyle) -> Stripper {
+    let stripper = Stripper::new(style)
+        .remove_blanks(config.remove_blanks)
+        .keep_doc_comments(config.keep_doc_comments)
+        .strip_docstrings(config.strip_docstrings)
+        .remove_trailing_commas(config.remove_trailing_commas)
+        .keep_license_comments(config.keep_license_comments)
+        .keep_kinds(&config.keep_kinds)
+        .keep_header(config.keep_header)
+        .keep_directives(config.keep_directives);
+    let stripper = config.keep_matching.iter().cloned().fold(stripper, Stripper::keep_matching);
+    config.directives.iter().cloned().fold(stripper, Stripper::directive)
+}
+
+/// How much of the input is looked at to detect the style when streaming
+const DETECT_BYTES: u64 = 1024;
+
+/// Counts the bytes read or written through it
+struct Counted<T> {
+    inner: T,
+    bytes: u64
+}
+
+impl<T> Counted<T> {
+    fn new(inner: T) -> Self {
+        Counted { inner, bytes: 0 }
+    }
+}
+
+impl<T: Read> Read for Counted<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        Ok(read)
+    }
+}
+
+impl<T: Write> Write for Counted<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    files: usize,
+    /// files without a known comment style
+    skipped: usize,
+    bytes_removed: u64,
+    comments: usize,
+    files_with_comments: usize,
+    failures: usize
+}
+
+impl Summary {
+    fn describe(&self, mode: Mode) -> String {
+        match mode {
+            Mode::Strip => format!("{} stripped, {} skipped, {} removed, {} failed",
+                                   plural(self.files as u64, "file"), self.skipped,
+                                   plural(self.bytes_removed, "byte"), self.failures),
+            Mode::Report { .. } => format!("{} checked, {} with comments, {}, {} skipped, {} failed",
+                                           plural(self.files as u64, "file"), self.files_with_comments,
+                                           plural(self.comments as u64, "comment"), self.skipped, self.failures),
+            Mode::Extract { .. } => format!("{} extracted, {}, {} skipped, {} failed",
+                                            plural(self.files as u64, "file"), plural(self.comments as u64, "comment"),
+                                            self.skipped, self.failures)
+        }
+    }
+
+    fn exit_code(&self, mode: Mode) -> i32 {
+        match mode {
+            _ if self.failures > 0 => EXIT_ERROR,
+            Mode::Report { check: true, .. } if self.comments > 0 => EXIT_COMMENTS,
+            _ => EXIT_CLEAN
+        }
+    }
+}
+
+/// `1 comment`, `2 comments`
+fn plural(count: u64, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+/// Rewrite `-i.bak` to `--in-place=.bak` like `sed` takes it, clap only accepts the optional
+/// suffix after `=`. Arguments after `--` are left alone.
+fn attached_suffix<I: Iterator<Item = OsString>>(args: I) -> Vec<OsString> {
+    let mut options = true;
+    args.map(|arg| {
+        match arg.to_str() {
+            Some("--") => options = false,
+            Some(a) if options && a.len() > 2 && a.starts_with("-i") && !a.starts_with("-i=") =>
+                return OsString::from(format!("--in-place={}", &a[2..])),
+            _ => {}
+        }
+        arg
+    }).collect()
+}
+
+fn open_output(config: &Config, job: &Job) -> Result<Output, AppError> {
+    if let (true, Some(path)) = (job.replace, job.output.as_ref()) {
+        let backup = match config.target {
+            Target::InPlace(ref suffix) => suffix.as_deref(),
+            _ => None
+        };
+        return Output::replace(path, backup, config.preserve_times)
+            .map_err(|e| AppError::Io(job.output_name(), e));
+    }
+    let dir = job.output.as_ref()
+        .and_then(|p| p.parent())
+        .filter(|p| !p.as_os_str().is_empty());
+    dir.map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| Output::from_arg(job.output.as_ref()))
+        .map_err(|e| AppError::Io(job.output_name(), e))
+}
+
+/// Flush the writer and replace the original file if needed
+fn finish(writer: BufWriter<Output>) -> io::Result<()> {
+    writer.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(Output::finish)
+}
+
+/// Strip one input and return the number of bytes removed
+fn strip_stream(config: &Config, job: &Job) -> Result<u64, AppError> {
+    let input_name = job.input_name();
+    let mut input = Input::from_arg(job.input.as_ref())
+        .map_err(|e| AppError::Io(input_name.clone(), e))?;
+    let mut head = Vec::new();
+    input.by_ref().take(DETECT_BYTES).read_to_end(&mut head)
+        .map_err(|e| AppError::Io(input_name.clone(), e))?;
+    let stripper = stripper(config, style(config, job, &head)?);
+    let mut reader = Counted::new(BufReader::new(io::Cursor::new(head).chain(input)));
+    let mut writer = Counted::new(BufWriter::new(open_output(config, job)?));
+    stripper.strip_stream(&mut reader, &mut writer)
+        .map_err(|e| match e {
+            StreamError::Read(e) => AppError::Io(input_name, e),
+            StreamError::Write(e) => AppError::Io(job.output_name(), e),
+            StreamError::Strip(e) => AppError::Strip(input_name, e)
+        })?;
+    let written = writer.bytes;
+    finish(writer.inner)
+        .map_err(|e| AppError::Io(job.output_name(), e))?;
+    Ok(reader.bytes.saturating_sub(written))
+}
+
+fn read(job: &Job) -> Result<Vec<u8>, AppError> {
+    let mut data = Vec::new();
+    Input::from_arg(job.input.as_ref())
+        .and_then(|input| BufReader::new(input).read_to_end(&mut data))
+        .map_err(|e| AppError::Io(job.input_name(), e))?;
+    Ok(data)
+}
+
+/// Read and decode one input and select its stripper
+fn load(config: &Config, job: &Job) -> Result<(String, Stripper), AppError> {
+    let data = read(job)?;
+    let stripper = stripper(config, style(config, job, &data)?);
+    let data = from_utf8(data)
+        .map_err(|e| AppError::Strip(job.input_name(), e))?;
+    Ok((data, stripper))
+}
+
+fn write_output(config: &Config, job: &Job, text: &str) -> Result<(), AppError> {
+    let mut bw = BufWriter::new(open_output(config, job)?);
+    bw.write_all(text.as_bytes())
+        .and_then(|_| finish(bw))
+        .map_err(|e| AppError::Io(job.output_name(), e))
+}
+
+/// Strip one input and return the number of bytes removed
+fn strip(config: &Config, job: &Job) -> Result<u64, AppError> {
+    let (data, stripper) = load(config, job)?;
+    let stripped = stripper.strip(data.as_str())
+        .map_err(|e| AppError::Strip(job.input_name(), e))?;
+    write_output(config, job, &stripped)?;
+    Ok((data.len() - stripped.len()) as u64)
+}
+
+/// Write only the comments of one input and return how many there are
+fn extract(config: &Config, job: &Job, trim_delimiters: bool, prefix: bool) -> Result<usize, AppError> {
+    let (data, stripper) = load(config, job)?;
+    let matches = stripper.find_comments(data.as_str())
+        .map_err(|e| AppError::Strip(job.input_name(), e))?;
+    let extracted = if prefix {
+        report::extract(&job.input_name(), &data, &matches, stripper.style(), trim_delimiters)
+    } else {
+        stripper.extract(data.as_str(), trim_delimiters)
+            .map_err(|e| AppError::Strip(job.input_name(), e))?
+    };
+    write_output(config, job, &extracted)?;
+    Ok(matches.len())
+}
+
+fn print(text: &str) -> Result<(), AppError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    out.write_all(text.as_bytes())
+        .and_then(|_| out.flush())
+        .map_err(|e| AppError::Io("<stdout>".to_owned(), e))
+}
+
+/// Find the comments of one input, print them if `list` and return how many there are.
+/// As JSON they are only collected in `records` to be printed at the end.
+fn report(config: &Config, job: &Job, list: bool, records: &mut Vec<serde_json::Value>)
+    -> Result<usize, AppError> {
+    let (data, stripper) = load(config, job)?;
+    let matches = stripper.find_comments(data.as_str())
+        .map_err(|e| AppError::Strip(job.input_name(), e))?;
+    if !list {
+        return Ok(matches.len());
+    }
+    let path = job.input.as_ref().map(|p| p.display().to_string());
+    let json = || report::json(path.as_deref(), stripper.style(), &data, &matches);
+    match config.format {
+        Format::Text => print(&report::list(&job.input_name(), &data, &matches))?,
+        Format::Ndjson => print(&json().iter().map(|r| format!("{}\n", r)).collect::<String>())?,
+        Format::Json => records.extend(json())
+    }
+    Ok(matches.len())
+}
 
-fn doit(config: Config) -> Result<(), AppError> {
-    let mut data = String::new();
-    let mut br = BufReader::new(config.input);
-    br.read_to_string(&mut data)?;
-    let stripped = strip_comments(data, config.style, config.remove_blanks)?;
-    let mut bw = BufWriter::new(config.output);
-    bw.write_all(stripped.as_bytes())?;
-    Ok(())
+/// Process all inputs, failures are reported and do not stop the remaining inputs.
+/// When processing several inputs, files of unknown style are skipped.
+fn doit(config: &Config) -> Result<Summary, AppError> {
+    let (jobs, errors) = files::jobs(config)?;
+    let mut summary = Summary::default();
+    let mut records = Vec::new();
+    for err in errors {
+        eprintln!("{}", err);
+        summary.failures += 1;
+    }
+    for job in &jobs {
+        let result = match config.mode {
+            Mode::Strip if config.stream => strip_stream(config, job)
+                .map(|removed| summary.bytes_removed += removed),
+            Mode::Strip => strip(config, job)
+                .map(|removed| summary.bytes_removed += removed),
+            Mode::Extract { trim_delimiters, prefix } => extract(config, job, trim_delimiters, prefix)
+                .map(|comments| summary.comments += comments),
+            Mode::Report { list, check } => report(config, job, list, &mut records)
+                .map(|comments| {
+                    if comments > 0 {
+                        summary.comments += comments;
+                        summary.files_with_comments += 1;
+                        if check && !list {
+                            eprintln!("{}: {}", job.input_name(), plural(comments as u64, "comment"));
+                        }
+                    }
+                })
+        };
+        match result {
+            Ok(()) => summary.files += 1,
+            Err(AppError::UnknownStyle(_)) if config.batch => summary.skipped += 1,
+            Err(err) => {
+                eprintln!("{}", err);
+                summary.failures += 1;
+            }
+        }
+    }
+    if config.format == Format::Json {
+        let json = serde_json::to_string_pretty(&records).unwrap_or_default();
+        print(&format!("{}\n", json))?;
+    }
+    Ok(summary)
 }
 
 fn main() {
     let yaml = load_yaml!("cli.yml");
-    let matches = App::from_yaml(yaml).get_matches();
-    let config = Config::from_matches(&matches).unwrap();
-    doit(config).unwrap();
+    /* the optional value needs `require_equals`, which cli.yml cannot express */
+    let in_place = Arg::with_name("in-place")
+        .short("i")
+        .long("in-place")
+        .value_name("SUFFIX")
+        .help("Atomically replace each input with its result, keeping a backup with SUFFIX if given like `-i.bak`")
+        .takes_value(true)
+        .min_values(0)
+        .require_equals(true)
+        .conflicts_with("output-dir");
+    let matches = App::from_yaml(yaml).arg(in_place).get_matches_from_safe(attached_suffix(env::args_os()))
+        .unwrap_or_else(|e| {
+            if e.use_stderr() {
+                eprintln!("{}", e.message);
+                process::exit(EXIT_ERROR);
+            }
+            e.exit()
+        });
+    let result = Config::from_matches(&matches).and_then(|config| {
+        let summary = doit(&config)?;
+        if config.batch {
+            eprintln!("{}", summary.describe(config.mode));
+        }
+        Ok(summary.exit_code(config.mode))
+    });
+    match result {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_ERROR);
+        }
+    }
 }
@@ -0,0 +1,161 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    /// only whitespace so far on this line, `#` and `!` start a comment
+    LineStart,
+    /// key or value, comments only span whole lines
+    Normal,
+    /// a backslash, before a line break it continues the value on the next line
+    Escaped,
+    /// leading whitespace of a continuation line, which is never a comment
+    Continuation,
+    Comment,
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::LineStart
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentStarts,
+    CommentEnds
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\n'    => (ParseState::LineStart, ParseAction::Nothing),
+        '\\'    => (ParseState::Escaped, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Nothing)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::LineStart => match c {
+                '#' | '!' => (ParseState::Comment, ParseAction::CommentStarts),
+                c if c.is_whitespace() => (ParseState::LineStart, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::Normal => normal(c),
+            ParseState::Escaped => match c {
+                '\n'    => (ParseState::Continuation, ParseAction::Nothing),
+                '\r'    => (ParseState::Escaped, ParseAction::Nothing),
+                _       => (ParseState::Normal, ParseAction::Nothing)
+            },
+            ParseState::Continuation => match c {
+                '\n'    => (ParseState::LineStart, ParseAction::Nothing),
+                c if c.is_whitespace() => (ParseState::Continuation, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            /* a comment line cannot be continued, a backslash at its end means nothing */
+            ParseState::Comment => match c {
+                '\n'    => (ParseState::LineStart, ParseAction::CommentEnds),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::Comment => (ParseState::End, ParseAction::CommentEnds),
+            _ => (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentState {
+    NotInComment,
+    InComment(usize)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentStarts => {
+            comment_state = CommentState::InComment(position);
+        },
+        ParseAction::CommentEnds => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    matches.push(CommentMatch { from, to: position, kind: CommentKind::Line });
+                    comment_state = CommentState::NotInComment;
+                },
+                CommentState::NotInComment => {
+                    return Err(ScanError::new(ErrorKind::Parser("properties style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition, do_action)
+}
+
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_comments() {
+        let input = "# one\n  ! two\nkey=value\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 5, kind: CommentKind::Line },
+            CommentMatch { from: 8, to: 13, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn only_whole_lines() {
+        let input = "url = http://x/#top ! not\ncolor=#fff\n";
+        let expected = Ok(Vec::new());
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn line_continuation() {
+        let input = "list = a, \\\n    # b, \\\r\n    ! c\n# comment \\\n! next\nkey = a\\\\\n# comment too\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 32, to: 43, kind: CommentKind::Line },
+            CommentMatch { from: 44, to: 50, kind: CommentKind::Line },
+            CommentMatch { from: 61, to: 74, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+}
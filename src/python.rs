@@ -0,0 +1,500 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// Delimiter of the string literal currently scanned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Quote {
+    ch: char,
+    triple: bool,
+    format: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Start,
+    /// indentation of line 1 or 2, where encoding declarations may appear
+    Header(usize),
+    ShebangOrComment,
+    /// comment on line 1 or 2, counting the chars of `coding` matched so far
+    HeaderComment(usize, usize),
+    /// shebang or encoding declaration that is kept
+    KeptLine(usize),
+    Normal,
+    Continuation,
+    Identifier,
+    /// identifier that might be the given keyword, with the chars matched so far
+    Word(&'static str, usize),
+    /// letters that might prefix a string, `true` for f-strings
+    Prefix(bool),
+    Opening(Quote),
+    OpeningSecond(Quote),
+    TripleOpened(Quote),
+    String(Quote),
+    StringEscaped(Quote),
+    StringClosing(Quote, usize),
+    FormatBrace(Quote),
+    FormatField(Quote, usize),
+    FormatFieldString(Quote, usize, char),
+    Comment,
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Start
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentStarts,
+    CommentEnds,
+    ShebangFound,
+    EncodingFound,
+    /// any token that is not a string, bracket or colon
+    Code,
+    Open,
+    Close,
+    Colon,
+    /// `def` or `class`, found on the char after the keyword
+    Definition,
+    Newline,
+    StringStarts,
+    StringContentStarts,
+    /// string content ends before the last n chars, which are quotes
+    StringEnds(usize)
+}
+
+const CODING: &[char] = &['c', 'o', 'd', 'i', 'n', 'g'];
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_prefix(c: char) -> bool {
+    "rRbBuUfF".contains(c)
+}
+
+fn next_line(line: usize) -> ParseState {
+    if line == 1 {
+        ParseState::Header(2)
+    } else {
+        ParseState::Normal
+    }
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '#'     => (ParseState::Comment, ParseAction::CommentStarts),
+        '"' | '\'' => (ParseState::Opening(Quote { ch: c, triple: false, format: false }),
+                    ParseAction::StringStarts),
+        '\\'    => (ParseState::Continuation, ParseAction::Nothing),
+        '\n'    => (ParseState::Normal, ParseAction::Newline),
+        '(' | '[' | '{' => (ParseState::Normal, ParseAction::Open),
+        ')' | ']' | '}' => (ParseState::Normal, ParseAction::Close),
+        ':'     => (ParseState::Normal, ParseAction::Colon),
+        c if is_prefix(c) => (ParseState::Prefix(c == 'f' || c == 'F'), ParseAction::Nothing),
+        'd'     => (ParseState::Word("def", 1), ParseAction::Code),
+        'c'     => (ParseState::Word("class", 1), ParseAction::Code),
+        c if is_identifier(c) => (ParseState::Identifier, ParseAction::Code),
+        c if c.is_whitespace() => (ParseState::Normal, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Code)
+    }
+}
+
+fn header(line: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        ' ' | '\t' | '\x0c' => (ParseState::Header(line), ParseAction::Nothing),
+        '#'     => (ParseState::HeaderComment(line, 0), ParseAction::CommentStarts),
+        '\n'    => (next_line(line), ParseAction::Newline),
+        _       => normal(c)
+    }
+}
+
+/* PEP 263: a comment on line 1 or 2 matching `coding[:=]` */
+fn header_comment(line: usize, matched: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\n'    => (next_line(line), ParseAction::CommentEnds),
+        ':' | '=' if matched == CODING.len() =>
+            (ParseState::KeptLine(line), ParseAction::EncodingFound),
+        c if matched < CODING.len() && c == CODING[matched] =>
+            (ParseState::HeaderComment(line, matched + 1), ParseAction::Nothing),
+        'c'     => (ParseState::HeaderComment(line, 1), ParseAction::Nothing),
+        _       => (ParseState::HeaderComment(line, 0), ParseAction::Nothing)
+    }
+}
+
+fn string(quote: Quote, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\\'    => (ParseState::StringEscaped(quote), ParseAction::Nothing),
+        c if c == quote.ch && quote.triple =>
+            (ParseState::StringClosing(quote, 1), ParseAction::Nothing),
+        c if c == quote.ch => (ParseState::Normal, ParseAction::StringEnds(1)),
+        /* unterminated single line string */
+        '\n' if !quote.triple => (ParseState::Normal, ParseAction::StringEnds(1)),
+        '{' if quote.format => (ParseState::FormatBrace(quote), ParseAction::Nothing),
+        _       => (ParseState::String(quote), ParseAction::Nothing)
+    }
+}
+
+fn format_field(quote: Quote, depth: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '{'     => (ParseState::FormatField(quote, depth + 1), ParseAction::Nothing),
+        '}' if depth == 1 => (ParseState::String(quote), ParseAction::Nothing),
+        '}'     => (ParseState::FormatField(quote, depth - 1), ParseAction::Nothing),
+        '"' | '\'' => (ParseState::FormatFieldString(quote, depth, c), ParseAction::Nothing),
+        _       => (ParseState::FormatField(quote, depth), ParseAction::Nothing)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::Start => match c {
+                '#'     => (ParseState::ShebangOrComment, ParseAction::CommentStarts),
+                _       => header(1, c)
+            },
+            ParseState::Header(line) => header(line, c),
+            ParseState::ShebangOrComment => match c {
+                '!'     => (ParseState::KeptLine(1), ParseAction::ShebangFound),
+                _       => header_comment(1, 0, c)
+            },
+            ParseState::HeaderComment(line, matched) => header_comment(line, matched, c),
+            ParseState::KeptLine(line) => match c {
+                '\n'    => (next_line(line), ParseAction::Newline),
+                _       => (ParseState::KeptLine(line), ParseAction::Nothing)
+            },
+            ParseState::Normal => normal(c),
+            ParseState::Continuation => match c {
+                '\n'    => (ParseState::Normal, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::Identifier => match c {
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::Word(keyword, matched) => match c {
+                c if keyword[matched..].starts_with(c) =>
+                    (ParseState::Word(keyword, matched + 1), ParseAction::Nothing),
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+                ' ' | '\t' if matched == keyword.len() => (ParseState::Normal, ParseAction::Definition),
+                _       => normal(c)
+            },
+            ParseState::Prefix(format) => match c {
+                c if is_prefix(c) => (ParseState::Prefix(format || c == 'f' || c == 'F'), ParseAction::Nothing),
+                '"' | '\'' => (ParseState::Opening(Quote { ch: c, triple: false, format }),
+                            ParseAction::StringStarts),
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Code),
+                /* the prefix was an identifier on its own */
+                _       => match normal(c) {
+                    (state, ParseAction::Nothing) => (state, ParseAction::Code),
+                    other => other
+                }
+            },
+            ParseState::Opening(quote) => match c {
+                c if c == quote.ch => (ParseState::OpeningSecond(quote), ParseAction::Nothing),
+                '\n'    => (ParseState::Normal, ParseAction::Newline),
+                _       => (string(quote, c).0, ParseAction::StringContentStarts)
+            },
+            /* two quotes are either an empty string or the start of a triple quoted one */
+            ParseState::OpeningSecond(quote) => match c {
+                c if c == quote.ch =>
+                    (ParseState::TripleOpened(Quote { triple: true, ..quote }), ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::TripleOpened(quote) =>
+                (string(quote, c).0, ParseAction::StringContentStarts),
+            ParseState::String(quote) => string(quote, c),
+            ParseState::StringEscaped(quote) =>
+                (ParseState::String(quote), ParseAction::Nothing),
+            ParseState::StringClosing(quote, seen) => match c {
+                c if c == quote.ch && seen == 2 => (ParseState::Normal, ParseAction::StringEnds(3)),
+                c if c == quote.ch => (ParseState::StringClosing(quote, seen + 1), ParseAction::Nothing),
+                _       => string(quote, c)
+            },
+            /* `{{` is an escaped brace, anything else opens a replacement field */
+            ParseState::FormatBrace(quote) => match c {
+                '{'     => (ParseState::String(quote), ParseAction::Nothing),
+                _       => format_field(quote, 1, c)
+            },
+            ParseState::FormatField(quote, depth) => format_field(quote, depth, c),
+            ParseState::FormatFieldString(quote, depth, inner) => match c {
+                c if c == inner => (ParseState::FormatField(quote, depth), ParseAction::Nothing),
+                _       => (ParseState::FormatFieldString(quote, depth, inner), ParseAction::Nothing)
+            },
+            ParseState::Comment => match c {
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::ShebangOrComment |
+            ParseState::HeaderComment(_, _) |
+            ParseState::Comment => (ParseState::End, ParseAction::CommentEnds),
+            _ => (ParseState::End, ParseAction::Newline)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+enum Docstring {
+    NotInDocstring,
+    /// a string started where a docstring may appear
+    Candidate,
+    InDocstring(usize),
+    /// content range of a docstring, confirmed once the statement ends
+    AfterDocstring(usize, usize)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CommentState {
+    comment: Option<usize>,
+    docstring: Docstring,
+    /// bracket nesting, newlines inside brackets do not end a statement
+    depth: usize,
+    statement_start: bool,
+    after_colon: bool,
+    /// the statement is a `def` or `class` header
+    definition: bool,
+    expect_docstring: bool
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState {
+            comment: None,
+            docstring: Docstring::NotInDocstring,
+            depth: 0,
+            statement_start: true,
+            after_colon: false,
+            definition: false,
+            expect_docstring: true
+        }
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        let docstring = match self.docstring {
+            Docstring::InDocstring(from) | Docstring::AfterDocstring(from, _) => Some(from),
+            _ => None
+        };
+        match (docstring, self.comment) {
+            (Some(d), Some(c)) => Some(d.min(c)),
+            (d, c) => d.or(c)
+        }
+    }
+}
+
+fn token(mut state: CommentState, action: ParseAction) -> CommentState {
+    match action {
+        ParseAction::Open => state.depth += 1,
+        ParseAction::Close => state.depth = state.depth.saturating_sub(1),
+        _ => {}
+    }
+    state.docstring = Docstring::NotInDocstring;
+    state.statement_start = false;
+    state.expect_docstring = false;
+    state.after_colon = action == ParseAction::Colon && state.depth == 0;
+    state
+}
+
+/* docstrings are the first statement of the module, a class or a function */
+fn newline(mut state: CommentState, matches: &mut Vec<CommentMatch>) -> CommentState {
+    if state.depth > 0 {
+        return state;
+    }
+    if let Docstring::AfterDocstring(from, to) = state.docstring {
+        matches.push(CommentMatch{from, to, kind: CommentKind::Doc});
+    }
+    state.docstring = Docstring::NotInDocstring;
+    if !state.statement_start {
+        state.expect_docstring = state.after_colon && state.definition;
+        state.statement_start = true;
+        state.definition = false;
+    }
+    state
+}
+
+fn do_action(strip_docstrings: bool, action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentStarts => {
+            comment_state.comment = Some(position);
+        },
+        ParseAction::ShebangFound | ParseAction::EncodingFound => {
+            comment_state.comment = None;
+        },
+        ParseAction::CommentEnds => {
+            comment_state = newline(comment_state, &mut matches);
+            match comment_state.comment {
+                Some(from) => {
+                    matches.push(CommentMatch{from, to: position, kind: CommentKind::Line});
+                    comment_state.comment = None;
+                },
+                None => {
+                    return Err(ScanError::new(ErrorKind::Parser("python style"), position));
+                }
+            }
+        },
+        ParseAction::Code | ParseAction::Open | ParseAction::Close | ParseAction::Colon => {
+            comment_state = token(comment_state, action);
+        },
+        ParseAction::Definition => {
+            if comment_state.depth == 0 {
+                comment_state.definition = true;
+            }
+        },
+        ParseAction::StringStarts => {
+            let candidate = strip_docstrings
+                && comment_state.statement_start
+                && comment_state.expect_docstring;
+            comment_state = token(comment_state, action);
+            if candidate {
+                comment_state.docstring = Docstring::Candidate;
+            }
+        },
+        ParseAction::StringContentStarts => {
+            if comment_state.docstring == Docstring::Candidate {
+                comment_state.docstring = Docstring::InDocstring(position);
+            }
+        },
+        ParseAction::StringEnds(quotes) => {
+            if let Docstring::InDocstring(from) = comment_state.docstring {
+                /* quotes are ascii, so each is one byte */
+                comment_state.docstring = Docstring::AfterDocstring(from, position + 1 - quotes);
+            }
+        },
+        ParseAction::Newline => {
+            comment_state = newline(comment_state, &mut matches);
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str, strip_docstrings: bool) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition,
+        move |a, c, p, m| do_action(strip_docstrings, a, c, p, m))
+}
+
+pub fn scanner(strip_docstrings: bool) -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition,
+        move |a, c, p, m| do_action(strip_docstrings, a, c, p, m)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CommentMatch;
+
+    #[test]
+    fn no_comment_present() {
+        let input = "x = 1\nprint(x)\n";
+        let expected = Ok(Vec::new());
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn normal_comment() {
+        let input = "x = 1  # one\n# two\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 7, to: 12, kind: CommentKind::Line },
+            CommentMatch { from: 13, to: 18, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_comment_in_triple_quoted_string() {
+        let input = "s = '''a # b\n' # c''' # d\nt = \"\"\"# e\"\"\"";
+        let expected = Ok(vec![
+            CommentMatch { from: 22, to: 25, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_comment_in_prefixed_string() {
+        let input = "a = rb'#' + Rb\"\\\"#\" + f'{x[\"#\"]}#' # c";
+        let expected = Ok(vec![
+            CommentMatch { from: 35, to: 38, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn prefix_letters_as_identifiers() {
+        let input = "f = r # c\nb(u) # d";
+        let expected = Ok(vec![
+            CommentMatch { from: 6, to: 9, kind: CommentKind::Line },
+            CommentMatch { from: 15, to: 18, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn keeps_shebang_and_encoding() {
+        let input = "#!/usr/bin/env python3\n# -*- coding: utf-8 -*-\n# other\nx = 1 # vim: fileencoding=utf-8\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 47, to: 54, kind: CommentKind::Line },
+            CommentMatch { from: 61, to: 86, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn encoding_only_on_first_two_lines() {
+        let input = "\n\n# coding=latin-1\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 2, to: 18, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn docstrings_kept_by_default() {
+        let input = "\"\"\"Module.\"\"\"\ndef f():\n    \"\"\"Function.\"\"\"\n";
+        let expected = Ok(Vec::new());
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn strips_docstrings() {
+        let input = "\"\"\"Module.\"\"\"\nclass A:\n    'Class.'  # c\n    def f(self):\n        r'''Function.'''\n        return 1\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 3, to: 10, kind: CommentKind::Doc },
+            CommentMatch { from: 28, to: 34, kind: CommentKind::Doc },
+            CommentMatch { from: 37, to: 40, kind: CommentKind::Line },
+            CommentMatch { from: 70, to: 79, kind: CommentKind::Doc }
+        ]);
+        let actual = find_comments(input, true);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn strings_that_are_not_docstrings() {
+        let input = "x = 1\n\"\"\"a\"\"\"\ndef f():\n    \"b\".join(y)\n    \"c\"\nd = {\n    'k':\n    'v'}\nif x:\n    'e'\ndefine:\n    'f'\n";
+        let expected = Ok(Vec::new());
+        let actual = find_comments(input, true);
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,156 @@
+extern crate tempfile;
+
+use std::ffi::OsString;
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use self::tempfile::{Builder, NamedTempFile};
+
+/// Writes to a temporary file in the same directory, which atomically replaces
+/// the original file on `commit`. Dropping it without `commit` leaves the original untouched.
+#[derive(Debug)]
+pub struct Replacement {
+    path: PathBuf,
+    temp: NamedTempFile,
+    backup: Option<PathBuf>,
+    times: Option<FileTimes>
+}
+
+impl Replacement {
+    /// The permissions of `path` are kept, its access and modification times only if `preserve_times`
+    pub fn new(path: &Path, backup_suffix: Option<&str>, preserve_times: bool) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new(".")
+        };
+        let temp = Builder::new().prefix(".comment-strip").tempfile_in(dir)?;
+        temp.as_file().set_permissions(metadata.permissions())?;
+        let times = if preserve_times {
+            Some(FileTimes::new()
+                .set_accessed(metadata.accessed()?)
+                .set_modified(metadata.modified()?))
+        } else {
+            None
+        };
+        let backup = backup_suffix.map(|suffix| {
+            let mut name = OsString::from(path.as_os_str());
+            name.push(suffix);
+            PathBuf::from(name)
+        });
+        Ok(Replacement { path: path.to_owned(), temp, backup, times })
+    }
+
+    /// Sync the new content to disk, keep the backup if requested and move it in place
+    pub fn commit(self) -> io::Result<()> {
+        let Replacement { path, temp, backup, times } = self;
+        if let Some(times) = times {
+            temp.as_file().set_times(times)?;
+        }
+        temp.as_file().sync_all()?;
+        if let Some(backup) = backup {
+            keep_backup(&path, &backup)?;
+        }
+        temp.persist(&path).map_err(|e| e.error)?;
+        sync_dir(&path)
+    }
+}
+
+impl Write for Replacement {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.temp.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.temp.flush()
+    }
+}
+
+fn keep_backup(path: &Path, backup: &Path) -> io::Result<()> {
+    if let Err(e) = fs::remove_file(backup) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    /* a hard link costs nothing, but is not supported everywhere */
+    fs::hard_link(path, backup)
+        .or_else(|_| fs::copy(path, backup).map(|_| ()))
+}
+
+/// Make the rename itself durable
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all()
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    /// Directory with `a.txt` containing `original`
+    fn file() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "original").unwrap();
+        (dir, path)
+    }
+
+    fn entries(dir: &Path) -> Vec<OsString> {
+        let mut names: Vec<_> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn replaces_and_keeps_backup() {
+        let (dir, path) = file();
+        let mut replacement = Replacement::new(&path, Some(".bak"), false).unwrap();
+        replacement.write_all(b"stripped").unwrap();
+        replacement.commit().unwrap();
+        assert_eq!("stripped", fs::read_to_string(&path).unwrap());
+        assert_eq!("original", fs::read_to_string(dir.path().join("a.txt.bak")).unwrap());
+        assert_eq!(vec![OsString::from("a.txt"), OsString::from("a.txt.bak")], entries(dir.path()));
+    }
+
+    #[test]
+    fn untouched_without_commit() {
+        let (dir, path) = file();
+        {
+            let mut replacement = Replacement::new(&path, Some(".bak"), false).unwrap();
+            replacement.write_all(b"half").unwrap();
+        }
+        assert_eq!("original", fs::read_to_string(&path).unwrap());
+        assert_eq!(vec![OsString::from("a.txt")], entries(dir.path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let (_dir, path) = file();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let replacement = Replacement::new(&path, None, false).unwrap();
+        replacement.commit().unwrap();
+        assert_eq!(0o640, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+    }
+
+    #[test]
+    fn preserves_times() {
+        let (_dir, path) = file();
+        let modified = SystemTime::now() - Duration::from_secs(86_400);
+        File::options().write(true).open(&path).unwrap()
+            .set_times(FileTimes::new().set_accessed(modified).set_modified(modified)).unwrap();
+        Replacement::new(&path, None, true).unwrap().commit().unwrap();
+        assert_eq!(modified, fs::metadata(&path).unwrap().modified().unwrap());
+        Replacement::new(&path, None, false).unwrap().commit().unwrap();
+        assert!(fs::metadata(&path).unwrap().modified().unwrap() > modified);
+    }
+}
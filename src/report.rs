@@ -0,0 +1,100 @@
+use std::fmt::Write;
+use comment_strip::{locate_matches, CommentMatch, CommentStyle, Location};
+use serde_json::Value;
+
+/// One `path:line:col: text` line per comment, line breaks in the text are escaped
+pub fn list(path: &str, input: &str, matches: &[CommentMatch]) -> String {
+    let mut out = String::new();
+    for (m, (from, _)) in matches.iter().zip(locate_matches(input, matches)) {
+        let text = input[m.from..m.to]
+            .replace('\\', "\\\\")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n");
+        let _ = writeln!(out, "{}:{}:{}: {}", path, from.line, from.column, text);
+    }
+    out
+}
+
+/// Only the comments, each line prefixed with `path:line: `
+pub fn extract(path: &str, input: &str, matches: &[CommentMatch], style: &CommentStyle, trim_delimiters: bool)
+    -> String {
+    let mut out = String::new();
+    for (m, (from, _)) in matches.iter().zip(locate_matches(input, matches)) {
+        let comment = &input[m.from..m.to];
+        let text = if trim_delimiters {
+            style.trim_delimiters(comment)
+        } else {
+            comment
+        };
+        /* trimming may have dropped whole lines at the start, `text` is a slice of `comment` */
+        let skipped = text.as_ptr() as usize - comment.as_ptr() as usize;
+        let first_line = from.line + comment[..skipped].matches('\n').count();
+        for (i, line) in text.split('\n').enumerate() {
+            let _ = writeln!(out, "{}:{}: {}", path, first_line + i, line);
+        }
+    }
+    out
+}
+
+fn location(location: Location) -> Value {
+    json!({
+        "offset": location.offset,
+        "line": location.line,
+        "column": location.column
+    })
+}
+
+/// One JSON object per comment, `path` is `None` for stdin
+pub fn json(path: Option<&str>, style: &CommentStyle, input: &str, matches: &[CommentMatch]) -> Vec<Value> {
+    matches.iter().zip(locate_matches(input, matches))
+        .map(|(m, (from, to))| json!({
+            "path": path,
+            "style": style.name(),
+            "kind": m.kind.name(),
+            "start": location(from),
+            "end": location(to),
+            "text": &input[m.from..m.to]
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comment_strip::find_comments;
+
+    #[test]
+    fn lists_comments() {
+        let input = "int a; // x\\y\n/* ä\n */ b;\n";
+        let matches = find_comments(input, &CommentStyle::C).unwrap();
+        assert_eq!("a.c:1:8: // x\\\\y\na.c:2:1: /* ä\\n */\n", list("a.c", input, &matches));
+    }
+
+    #[test]
+    fn comments_as_json() {
+        let input = "a;\n/* ü */ // x\n";
+        let matches = find_comments(input, &CommentStyle::C).unwrap();
+        let records = json(Some("a.c"), &CommentStyle::C, input, &matches);
+        assert_eq!(json!({
+            "path": "a.c",
+            "style": "c",
+            "kind": "block",
+            "start": { "offset": 3, "line": 2, "column": 1 },
+            "end": { "offset": 11, "line": 2, "column": 8 },
+            "text": "/* ü */"
+        }), records[0]);
+        assert_eq!("line", records[1]["kind"]);
+        assert_eq!(Value::Null, json(None, &CommentStyle::C, input, &matches)[0]["path"]);
+    }
+
+    #[test]
+    fn extracts_with_prefix() {
+        let input = "a; # x\n# y\nb;\n";
+        let matches = find_comments(input, &CommentStyle::Shell).unwrap();
+        assert_eq!("s:1: x\ns:2: y\n", extract("s", input, &matches, &CommentStyle::Shell, true));
+        let input = "a; /*\n  x\n  y */\n";
+        let matches = find_comments(input, &CommentStyle::C).unwrap();
+        assert_eq!("c:2: x\nc:3:   y\n", extract("c", input, &matches, &CommentStyle::C, true));
+        assert_eq!("c:1: /*\nc:2:   x\nc:3:   y */\n", extract("c", input, &matches, &CommentStyle::C, false));
+    }
+}
@@ -0,0 +1,390 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Start,
+    Normal,
+    Identifier,
+    /// `b` or `c` that might prefix a string or byte literal
+    LiteralPrefix,
+    /// `r` that might start a raw string
+    RawPrefix,
+    RawHashes(usize),
+    RawString(usize),
+    RawStringClosing(usize, usize),
+    StringDoubleQuotes,
+    StringDoubleQuotesEscaped,
+    /// `'` that starts either a char literal or a lifetime
+    Quote,
+    QuoteChar,
+    CharLiteral,
+    CharLiteralEscaped,
+    FirstSlash,
+    SecondSlash,
+    ThirdSlash,
+    LineComment(bool),
+    BlockCommentStart,
+    BlockCommentStartStar,
+    BlockComment(usize, bool),
+    BlockCommentStar(usize, bool),
+    BlockCommentSlash(usize, bool),
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Start
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentMightStart,
+    CommentConfirmed,
+    CommentDismissed,
+    /// comment ends before the current char, `true` for doc comments
+    CommentEnds(bool),
+    /// comment ends after the current char, `true` for doc comments
+    CommentEndsAfter(bool),
+    CommentUnterminated
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '/'     => (ParseState::FirstSlash, ParseAction::CommentMightStart),
+        '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
+        '\''    => (ParseState::Quote, ParseAction::Nothing),
+        'r'     => (ParseState::RawPrefix, ParseAction::Nothing),
+        'b' | 'c' => (ParseState::LiteralPrefix, ParseAction::Nothing),
+        c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Nothing)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::Start | ParseState::Normal => normal(c),
+            ParseState::Identifier => match c {
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::LiteralPrefix => match c {
+                'r'     => (ParseState::RawPrefix, ParseAction::Nothing),
+                '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
+                '\''    => (ParseState::Quote, ParseAction::Nothing),
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::RawPrefix => match c {
+                '#'     => (ParseState::RawHashes(1), ParseAction::Nothing),
+                '"'     => (ParseState::RawString(0), ParseAction::Nothing),
+                c if is_identifier(c) => (ParseState::Identifier, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            /* `r#ident` is a raw identifier, not a string */
+            ParseState::RawHashes(n) => match c {
+                '#'     => (ParseState::RawHashes(n + 1), ParseAction::Nothing),
+                '"'     => (ParseState::RawString(n), ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::RawString(n) => match c {
+                '"' if n == 0 => (ParseState::Normal, ParseAction::Nothing),
+                '"'     => (ParseState::RawStringClosing(n, 0), ParseAction::Nothing),
+                _       => (ParseState::RawString(n), ParseAction::Nothing)
+            },
+            ParseState::RawStringClosing(n, seen) => match c {
+                '#' if seen + 1 == n => (ParseState::Normal, ParseAction::Nothing),
+                '#'     => (ParseState::RawStringClosing(n, seen + 1), ParseAction::Nothing),
+                '"'     => (ParseState::RawStringClosing(n, 0), ParseAction::Nothing),
+                _       => (ParseState::RawString(n), ParseAction::Nothing)
+            },
+            ParseState::StringDoubleQuotes => match c {
+                '"'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::StringDoubleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::StringDoubleQuotes, ParseAction::Nothing)
+            },
+            ParseState::StringDoubleQuotesEscaped =>
+                (ParseState::StringDoubleQuotes, ParseAction::Nothing),
+            ParseState::Quote => match c {
+                '\\'    => (ParseState::CharLiteralEscaped, ParseAction::Nothing),
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                _       => (ParseState::QuoteChar, ParseAction::Nothing)
+            },
+            /* a second quote right after one char closes a char literal,
+               anything else means this was a lifetime or label */
+            ParseState::QuoteChar => match c {
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::CharLiteral => match c {
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                _       => (ParseState::CharLiteral, ParseAction::Nothing)
+            },
+            ParseState::CharLiteralEscaped =>
+                (ParseState::CharLiteral, ParseAction::Nothing),
+            ParseState::FirstSlash => match c {
+                '/'     => (ParseState::SecondSlash, ParseAction::CommentConfirmed),
+                '*'     => (ParseState::BlockCommentStart, ParseAction::CommentConfirmed),
+                _       => (normal(c).0, ParseAction::CommentDismissed)
+            },
+            ParseState::SecondSlash => match c {
+                '/'     => (ParseState::ThirdSlash, ParseAction::Nothing),
+                '!'     => (ParseState::LineComment(true), ParseAction::Nothing),
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds(false)),
+                _       => (ParseState::LineComment(false), ParseAction::Nothing)
+            },
+            // `///` is a doc comment, `////` is not
+            ParseState::ThirdSlash => match c {
+                '/'     => (ParseState::LineComment(false), ParseAction::Nothing),
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds(true)),
+                _       => (ParseState::LineComment(true), ParseAction::Nothing)
+            },
+            ParseState::LineComment(doc) => match c {
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds(doc)),
+                _       => (ParseState::LineComment(doc), ParseAction::Nothing)
+            },
+            ParseState::BlockCommentStart => match c {
+                '*'     => (ParseState::BlockCommentStartStar, ParseAction::Nothing),
+                '!'     => (ParseState::BlockComment(1, true), ParseAction::Nothing),
+                '/'     => (ParseState::BlockCommentSlash(1, false), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(1, false), ParseAction::Nothing)
+            },
+            // `/**` is a doc comment, `/***` and `/**/` are not
+            ParseState::BlockCommentStartStar => match c {
+                '*'     => (ParseState::BlockCommentStar(1, false), ParseAction::Nothing),
+                '/'     => (ParseState::Normal, ParseAction::CommentEndsAfter(false)),
+                _       => (ParseState::BlockComment(1, true), ParseAction::Nothing)
+            },
+            ParseState::BlockComment(depth, doc) => match c {
+                '*'     => (ParseState::BlockCommentStar(depth, doc), ParseAction::Nothing),
+                '/'     => (ParseState::BlockCommentSlash(depth, doc), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth, doc), ParseAction::Nothing)
+            },
+            ParseState::BlockCommentStar(depth, doc) => match c {
+                '/' if depth == 1 => (ParseState::Normal, ParseAction::CommentEndsAfter(doc)),
+                '/'     => (ParseState::BlockComment(depth - 1, doc), ParseAction::Nothing),
+                '*'     => (ParseState::BlockCommentStar(depth, doc), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth, doc), ParseAction::Nothing)
+            },
+            ParseState::BlockCommentSlash(depth, doc) => match c {
+                '*'     => (ParseState::BlockComment(depth + 1, doc), ParseAction::Nothing),
+                '/'     => (ParseState::BlockCommentSlash(depth, doc), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth, doc), ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::FirstSlash =>
+                (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::SecondSlash =>
+                (ParseState::End, ParseAction::CommentEnds(false)),
+            ParseState::ThirdSlash =>
+                (ParseState::End, ParseAction::CommentEnds(true)),
+            ParseState::LineComment(doc) =>
+                (ParseState::End, ParseAction::CommentEnds(doc)),
+            ParseState::BlockCommentStart |
+            ParseState::BlockCommentStartStar |
+            ParseState::BlockComment(_, _) |
+            ParseState::BlockCommentStar(_, _) |
+            ParseState::BlockCommentSlash(_, _) =>
+                (ParseState::End, ParseAction::CommentUnterminated),
+            _ =>
+                (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+enum CommentState {
+    NotInComment,
+    MaybeInComment(usize),
+    InComment(usize)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
+fn do_action(keep_doc: bool, action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentMightStart => {
+            comment_state = CommentState::MaybeInComment(position);
+        },
+        ParseAction::CommentConfirmed => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    comment_state = CommentState::InComment(from);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("rust style"), position));
+                }
+            }
+        },
+        ParseAction::CommentDismissed => {
+            comment_state = CommentState::NotInComment;
+        },
+        ParseAction::CommentEnds(doc) | ParseAction::CommentEndsAfter(doc) => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    /* all comment delimiters are ascii, so the char is one byte */
+                    let (to, kind) = match action {
+                        ParseAction::CommentEndsAfter(_) => (position + 1, CommentKind::Block),
+                        _ => (position, CommentKind::Line)
+                    };
+                    let kind = if doc { CommentKind::Doc } else { kind };
+                    if !(doc && keep_doc) {
+                        matches.push(CommentMatch{from, to, kind});
+                    }
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("rust style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("rust style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str, keep_doc: bool) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition,
+        move |a, c, p, m| do_action(keep_doc, a, c, p, m))
+}
+
+pub fn scanner(keep_doc: bool) -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition,
+        move |a, c, p, m| do_action(keep_doc, a, c, p, m)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CommentMatch;
+
+    #[test]
+    fn no_comment_present() {
+        let input = "fn main() { let a = 1 / 2; }";
+        let expected = Ok(Vec::new());
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn line_and_block_comments() {
+        let input = "let a = 1; // one\n/* two */ let b = 2;";
+        let expected = Ok(vec![
+            CommentMatch { from: 11, to: 17, kind: CommentKind::Line },
+            CommentMatch { from: 18, to: 27, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn nested_block_comment() {
+        let input = "a /* outer /* inner */ still outer */ b";
+        let expected = Ok(vec![
+            CommentMatch { from: 2, to: 37, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn unterminated_nested_comment() {
+        let input = "a /* outer /* inner */";
+        let expected = Err(ScanError::new(ErrorKind::UnterminatedComment, 2).locate(input));
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_comment_in_raw_string() {
+        let input = "let s = r#\"// \" /* \"#; let t = br\"\\\"; // c";
+        let expected = Ok(vec![
+            CommentMatch { from: 38, to: 42, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn raw_identifier() {
+        let input = "let r#type = 1; // c";
+        let expected = Ok(vec![
+            CommentMatch { from: 16, to: 20, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn lifetimes_and_chars() {
+        let input = "fn f<'a>(s: &'a str) -> char { '/' } // c\nlet q = '\\''; b'\"'; // d";
+        let expected = Ok(vec![
+            CommentMatch { from: 37, to: 41, kind: CommentKind::Line },
+            CommentMatch { from: 62, to: 66, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input, false);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn keeps_doc_comments() {
+        let input = "//! crate\n/// item\n//// plain\n/** block doc */\n/*! inner */\n/**/\n/*** plain */\n";
+        let all = Ok(vec![
+            CommentMatch { from: 0, to: 9, kind: CommentKind::Doc },
+            CommentMatch { from: 10, to: 18, kind: CommentKind::Doc },
+            CommentMatch { from: 19, to: 29, kind: CommentKind::Line },
+            CommentMatch { from: 30, to: 46, kind: CommentKind::Doc },
+            CommentMatch { from: 47, to: 59, kind: CommentKind::Doc },
+            CommentMatch { from: 60, to: 64, kind: CommentKind::Block },
+            CommentMatch { from: 65, to: 78, kind: CommentKind::Block }
+        ]);
+        assert_eq!(all, find_comments(input, false));
+        let plain = Ok(vec![
+            CommentMatch { from: 19, to: 29, kind: CommentKind::Line },
+            CommentMatch { from: 60, to: 64, kind: CommentKind::Block },
+            CommentMatch { from: 65, to: 78, kind: CommentKind::Block }
+        ]);
+        assert_eq!(plain, find_comments(input, true));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{CommentMatch, Start, End, find_comments_impl};
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParseState {
@@ -29,7 +29,7 @@ impl End for ParseState {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParseAction {
     Nothing,
-    CommentStarts,
+    CommentStarts(CommentKind),
     CommentEnds,
     ShebangOrCommentStart,
     ShebangFound
@@ -45,7 +45,7 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
                 _       => (ParseState::Normal, ParseAction::Nothing)
             },
             ParseState::Normal => match c {
-                '#'     => (ParseState::Comment, ParseAction::CommentStarts),
+                '#'     => (ParseState::Comment, ParseAction::CommentStarts(CommentKind::Line)),
                 '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
                 '\''    => (ParseState::StringSingleQuotes, ParseAction::Nothing),
                 _       => (ParseState::Normal, ParseAction::Nothing)
@@ -56,7 +56,7 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
             },
             ParseState::Shebang => match c {
                 '\n'    => (ParseState::Normal, ParseAction::Nothing),
-                '#'     => (ParseState::Comment, ParseAction::CommentStarts),
+                '#'     => (ParseState::Comment, ParseAction::CommentStarts(CommentKind::Shebang)),
                 '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
                 '\''    => (ParseState::StringSingleQuotes, ParseAction::Nothing),
                 _       => (ParseState::Shebang, ParseAction::Nothing)
@@ -92,10 +92,11 @@ fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
 enum CommentState {
     NotInComment,
     MaybeInComment(usize),
-    InComment(usize)
+    InComment(usize, CommentKind)
 }
 
 impl Start for CommentState {
@@ -104,13 +105,22 @@ impl Start for CommentState {
     }
 }
 
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from, _) => Some(from)
+        }
+    }
+}
+
 fn do_action(action: ParseAction, mut comment_state: CommentState, 
             position: usize, mut matches: Vec<CommentMatch>) 
-    -> Result<(CommentState, Vec<CommentMatch>), &'static str> {
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
     match action {
         ParseAction::Nothing => {},
-        ParseAction::CommentStarts => {
-            comment_state = CommentState::InComment(position);
+        ParseAction::CommentStarts(kind) => {
+            comment_state = CommentState::InComment(position, kind);
         },
         ParseAction::ShebangOrCommentStart =>  {
             comment_state = CommentState::MaybeInComment(position);
@@ -121,14 +131,14 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
         ParseAction::CommentEnds => {
             match comment_state {
                 CommentState::NotInComment => {
-                    return Err("shell sytle parse error");
+                    return Err(ScanError::new(ErrorKind::Parser("shell style"), position));
                 },
                 CommentState::MaybeInComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                    matches.push(CommentMatch{from, to: position, kind: CommentKind::Line});
                     comment_state = CommentState::NotInComment;
                 },
-                CommentState::InComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                CommentState::InComment(from, kind) => {
+                    matches.push(CommentMatch{from, to: position, kind});
                     comment_state = CommentState::NotInComment;
                 }
             }
@@ -138,10 +148,14 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
 }
 
 
-pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, &'static str> {
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
     find_comments_impl(input, state_transition, do_action)
 }
 
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,7 +181,7 @@ mod tests {
     fn normal_comment() {
         let input = "yes # line comment\n yes no\n";
         let expected = Ok(vec![
-            CommentMatch { from: 4, to: 18}
+            CommentMatch { from: 4, to: 18, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -177,8 +191,8 @@ mod tests {
     fn multiple_comments() {
         let input = "yes # line comment\n# another comment with \"string\"\n yes no\n";
         let expected = Ok(vec![
-            CommentMatch { from: 4, to: 18 },
-            CommentMatch { from: 19, to: 50 }
+            CommentMatch { from: 4, to: 18, kind: CommentKind::Line },
+            CommentMatch { from: 19, to: 50, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -188,7 +202,7 @@ mod tests {
     fn comment_in_shebang() {
         let input = "#!/bin/bash #shebang\nyes\n";
         let expected = Ok(vec![
-            CommentMatch { from: 12, to: 20 }
+            CommentMatch { from: 12, to: 20, kind: CommentKind::Shebang }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -198,7 +212,7 @@ mod tests {
     fn no_final_newline() {
         let input = "yes #test";
         let expected = Ok(vec![
-            CommentMatch { from: 4, to: 9 }
+            CommentMatch { from: 4, to: 9, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -208,7 +222,17 @@ mod tests {
     fn no_comment_in_string() {
         let input = "yes 'string\"inner string\"' #test\n";
         let expected = Ok(vec![
-            CommentMatch { from: 27, to: 32 }
+            CommentMatch { from: 27, to: 32, kind: CommentKind::Line }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn multibyte_offsets() {
+        let input = "echo 'ü#' # grüße 🐚\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 11, to: 25, kind: CommentKind::Line }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
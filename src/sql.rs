@@ -0,0 +1,455 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// Flavour of SQL, they differ in comments, quoting and escapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    /// `--` and `/* */` comments, `''` escapes in strings
+    Standard,
+    /// nested block comments, `$tag$ ... $tag$` dollar quoting and backslash escapes in `E'...'` strings
+    PostgreSql,
+    /// `#` comments, `--` only before whitespace and backslash escapes in strings
+    MySql,
+    /// `[identifier]` quoting
+    Sqlite
+}
+
+impl SqlDialect {
+    pub const ALL: [SqlDialect; 4] = [SqlDialect::Standard, SqlDialect::PostgreSql, SqlDialect::MySql,
+                                      SqlDialect::Sqlite];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SqlDialect::Standard => "standard",
+            SqlDialect::PostgreSql => "postgresql",
+            SqlDialect::MySql => "mysql",
+            SqlDialect::Sqlite => "sqlite"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SqlDialect> {
+        SqlDialect::ALL.iter()
+            .find(|dialect| dialect.name() == name)
+            .cloned()
+    }
+}
+
+/// Tag of a PostgreSQL dollar quote like `$body$`, without the dollars
+type Tag = String;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseState {
+    Start,
+    Normal,
+    /// identifier, keyword or number, a `$` after it does not start a dollar quote
+    Word,
+    /// `E` or `e` that starts a string with backslash escapes in PostgreSQL if a `'` follows
+    EscapePrefix,
+    FirstDash,
+    /// `--` that is only a comment if followed by whitespace in MySQL
+    SecondDash,
+    FirstSlash,
+    LineComment,
+    BlockComment(usize),
+    BlockCommentStar(usize),
+    BlockCommentSlash(usize),
+    /// `''` inside a string is read as two adjacent strings, which is the same for finding comments
+    SingleQuotes,
+    SingleQuotesEscaped,
+    /// `E'...'` of PostgreSQL
+    EscapeString,
+    EscapeStringEscaped,
+    DoubleQuotes,
+    DoubleQuotesEscaped,
+    Backticks,
+    Brackets,
+    DollarTag(Tag),
+    DollarQuoted(Tag),
+    /// a `$` inside the dollar quoted text and the tag read after it
+    DollarClosing(Tag, Tag),
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Start
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentMightStart,
+    CommentConfirmed(CommentKind),
+    /// a comment starts at the current char, like `#` in MySQL
+    CommentStarts(CommentKind),
+    CommentDismissed,
+    /// comment ends before the current char
+    CommentEnds,
+    /// comment ends after the current char
+    CommentEndsAfter,
+    /// `--` followed by a line break is an empty comment
+    CommentConfirmedAndEnds,
+    CommentUnterminated
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_tag_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn normal(dialect: SqlDialect, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '-'     => (ParseState::FirstDash, ParseAction::CommentMightStart),
+        '/'     => (ParseState::FirstSlash, ParseAction::CommentMightStart),
+        '#' if dialect == SqlDialect::MySql =>
+                   (ParseState::LineComment, ParseAction::CommentStarts(CommentKind::Line)),
+        '\''    => (ParseState::SingleQuotes, ParseAction::Nothing),
+        '"'     => (ParseState::DoubleQuotes, ParseAction::Nothing),
+        '`'     => (ParseState::Backticks, ParseAction::Nothing),
+        '[' if dialect == SqlDialect::Sqlite => (ParseState::Brackets, ParseAction::Nothing),
+        '$' if dialect == SqlDialect::PostgreSql => (ParseState::DollarTag(Tag::new()), ParseAction::Nothing),
+        'E' | 'e' if dialect == SqlDialect::PostgreSql => (ParseState::EscapePrefix, ParseAction::Nothing),
+        c if is_word(c) => (ParseState::Word, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Nothing)
+    }
+}
+
+/// Leave a state that might have started a comment
+fn dismiss(dialect: SqlDialect, c: char) -> (ParseState, ParseAction) {
+    match normal(dialect, c) {
+        (state, ParseAction::Nothing) => (state, ParseAction::CommentDismissed),
+        other => other
+    }
+}
+
+fn state_transition(dialect: SqlDialect, from: ParseState, current_char: Option<char>)
+    -> (ParseState, ParseAction) {
+    let nested = dialect == SqlDialect::PostgreSql;
+    let backslash = dialect == SqlDialect::MySql;
+    match current_char {
+        Some(c) => match from {
+            ParseState::Start | ParseState::Normal => normal(dialect, c),
+            ParseState::Word => match c {
+                c if is_word(c) || c == '$' => (ParseState::Word, ParseAction::Nothing),
+                _       => normal(dialect, c)
+            },
+            ParseState::EscapePrefix => match c {
+                '\''    => (ParseState::EscapeString, ParseAction::Nothing),
+                c if is_word(c) || c == '$' => (ParseState::Word, ParseAction::Nothing),
+                _       => normal(dialect, c)
+            },
+            ParseState::FirstDash => match c {
+                '-' if dialect == SqlDialect::MySql => (ParseState::SecondDash, ParseAction::Nothing),
+                '-'     => (ParseState::LineComment, ParseAction::CommentConfirmed(CommentKind::Line)),
+                _       => dismiss(dialect, c)
+            },
+            ParseState::SecondDash => match c {
+                '\n'    => (ParseState::Normal, ParseAction::CommentConfirmedAndEnds),
+                c if c.is_whitespace() || c.is_control() =>
+                           (ParseState::LineComment, ParseAction::CommentConfirmed(CommentKind::Line)),
+                _       => dismiss(dialect, c)
+            },
+            ParseState::FirstSlash => match c {
+                '*'     => (ParseState::BlockComment(1), ParseAction::CommentConfirmed(CommentKind::Block)),
+                _       => dismiss(dialect, c)
+            },
+            ParseState::LineComment => match c {
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
+                _       => (ParseState::LineComment, ParseAction::Nothing)
+            },
+            ParseState::BlockComment(depth) => match c {
+                '*'     => (ParseState::BlockCommentStar(depth), ParseAction::Nothing),
+                '/' if nested => (ParseState::BlockCommentSlash(depth), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth), ParseAction::Nothing)
+            },
+            ParseState::BlockCommentStar(depth) => match c {
+                '/' if depth == 1 => (ParseState::Normal, ParseAction::CommentEndsAfter),
+                '/'     => (ParseState::BlockComment(depth - 1), ParseAction::Nothing),
+                '*'     => (ParseState::BlockCommentStar(depth), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth), ParseAction::Nothing)
+            },
+            ParseState::BlockCommentSlash(depth) => match c {
+                '*'     => (ParseState::BlockComment(depth + 1), ParseAction::Nothing),
+                '/'     => (ParseState::BlockCommentSlash(depth), ParseAction::Nothing),
+                _       => (ParseState::BlockComment(depth), ParseAction::Nothing)
+            },
+            ParseState::SingleQuotes => match c {
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                '\\' if backslash => (ParseState::SingleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::SingleQuotes, ParseAction::Nothing)
+            },
+            ParseState::SingleQuotesEscaped =>
+                (ParseState::SingleQuotes, ParseAction::Nothing),
+            ParseState::EscapeString => match c {
+                '\''    => (ParseState::Normal, ParseAction::Nothing),
+                '\\'    => (ParseState::EscapeStringEscaped, ParseAction::Nothing),
+                _       => (ParseState::EscapeString, ParseAction::Nothing)
+            },
+            ParseState::EscapeStringEscaped =>
+                (ParseState::EscapeString, ParseAction::Nothing),
+            ParseState::DoubleQuotes => match c {
+                '"'     => (ParseState::Normal, ParseAction::Nothing),
+                '\\' if backslash => (ParseState::DoubleQuotesEscaped, ParseAction::Nothing),
+                _       => (ParseState::DoubleQuotes, ParseAction::Nothing)
+            },
+            ParseState::DoubleQuotesEscaped =>
+                (ParseState::DoubleQuotes, ParseAction::Nothing),
+            ParseState::Backticks => match c {
+                '`'     => (ParseState::Normal, ParseAction::Nothing),
+                _       => (ParseState::Backticks, ParseAction::Nothing)
+            },
+            ParseState::Brackets => match c {
+                ']'     => (ParseState::Normal, ParseAction::Nothing),
+                _       => (ParseState::Brackets, ParseAction::Nothing)
+            },
+            /* `$1` is a parameter, not a dollar quote */
+            ParseState::DollarTag(mut tag) => match c {
+                '$'     => (ParseState::DollarQuoted(tag), ParseAction::Nothing),
+                c if is_tag_start(c) || (!tag.is_empty() && is_word(c)) => {
+                    tag.push(c);
+                    (ParseState::DollarTag(tag), ParseAction::Nothing)
+                },
+                c if is_word(c) => (ParseState::Word, ParseAction::Nothing),
+                _       => normal(dialect, c)
+            },
+            ParseState::DollarQuoted(tag) => match c {
+                '$'     => (ParseState::DollarClosing(tag, Tag::new()), ParseAction::Nothing),
+                _       => (ParseState::DollarQuoted(tag), ParseAction::Nothing)
+            },
+            ParseState::DollarClosing(tag, mut seen) => match c {
+                '$' if seen == tag => (ParseState::Normal, ParseAction::Nothing),
+                '$'     => (ParseState::DollarClosing(tag, Tag::new()), ParseAction::Nothing),
+                c if seen.len() < tag.len() && (is_tag_start(c) || (!seen.is_empty() && is_word(c))) => {
+                    seen.push(c);
+                    (ParseState::DollarClosing(tag, seen), ParseAction::Nothing)
+                },
+                _       => (ParseState::DollarQuoted(tag), ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::FirstDash | ParseState::SecondDash | ParseState::FirstSlash =>
+                (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::LineComment =>
+                (ParseState::End, ParseAction::CommentEnds),
+            ParseState::BlockComment(_) |
+            ParseState::BlockCommentStar(_) |
+            ParseState::BlockCommentSlash(_) =>
+                (ParseState::End, ParseAction::CommentUnterminated),
+            _ =>
+                (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+enum CommentState {
+    NotInComment,
+    MaybeInComment(usize),
+    InComment(usize, CommentKind)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::MaybeInComment(from) | CommentState::InComment(from, _) => Some(from)
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentMightStart => {
+            comment_state = CommentState::MaybeInComment(position);
+        },
+        ParseAction::CommentStarts(kind) => {
+            comment_state = CommentState::InComment(position, kind);
+        },
+        ParseAction::CommentConfirmed(kind) => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    comment_state = CommentState::InComment(from, kind);
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("sql style"), position));
+                }
+            }
+        },
+        ParseAction::CommentDismissed => {
+            comment_state = CommentState::NotInComment;
+        },
+        ParseAction::CommentEnds | ParseAction::CommentEndsAfter => {
+            match comment_state {
+                CommentState::InComment(from, kind) => {
+                    /* all comment delimiters are ascii, so the char is one byte */
+                    let to = match action {
+                        ParseAction::CommentEndsAfter => position + 1,
+                        _ => position
+                    };
+                    matches.push(CommentMatch { from, to, kind });
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("sql style"), position));
+                }
+            }
+        },
+        ParseAction::CommentConfirmedAndEnds => {
+            match comment_state {
+                CommentState::MaybeInComment(from) => {
+                    matches.push(CommentMatch { from, to: position, kind: CommentKind::Line });
+                    comment_state = CommentState::NotInComment;
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("sql style"), position));
+                }
+            }
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from, _) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("sql style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str, dialect: SqlDialect) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, move |s, c| state_transition(dialect, s, c), do_action)
+}
+
+pub fn scanner(dialect: SqlDialect) -> Box<dyn Scan> {
+    Box::new(Scanner::new(move |s, c| state_transition(dialect, s, c), do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::comment_texts;
+
+    #[test]
+    fn line_and_block_comments() {
+        let input = "SELECT 1 - 2; -- one\n/* two */ SELECT a/b FROM t;--\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 14, to: 20, kind: CommentKind::Line },
+            CommentMatch { from: 21, to: 30, kind: CommentKind::Block },
+            CommentMatch { from: 49, to: 51, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input, SqlDialect::Standard));
+    }
+
+    #[test]
+    fn no_comment_in_strings_and_identifiers() {
+        let input = "SELECT 'it''s -- no', \"a -- b\", `c /* d */` -- e";
+        let expected = vec![
+            ("-- e", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::Standard)));
+    }
+
+    #[test]
+    fn nested_block_comments_in_postgres() {
+        let input = "/* a /* b */ c */ SELECT 1;";
+        let expected = vec![
+            ("/* a /* b */ c */", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::PostgreSql)));
+        let expected = vec![
+            ("/* a /* b */", CommentKind::Block)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::Standard)));
+        let error = find_comments("/* a /* b */", SqlDialect::PostgreSql).unwrap_err();
+        assert_eq!(ErrorKind::UnterminatedComment, error.kind);
+    }
+
+    #[test]
+    fn dollar_quoting() {
+        let input = "CREATE FUNCTION f() AS $body$ -- no $b$ $$ /* $body$; -- yes\n\
+                     SELECT $$ -- no $$, $1 -- yes too\n\
+                     SELECT a$b$ -- identifier\n";
+        let expected = vec![
+            ("-- yes", CommentKind::Line),
+            ("-- yes too", CommentKind::Line),
+            ("-- identifier", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::PostgreSql)));
+    }
+
+    #[test]
+    fn dollar_tags_compared_exactly() {
+        let input = "SELECT $ab$ $ba$ -- no $abc$ $a$ $ab$; -- yes";
+        let expected = vec![
+            ("-- yes", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::PostgreSql)));
+    }
+
+    #[test]
+    fn escape_strings_in_postgres() {
+        let input = "SELECT E'it\\'s -- no' AS a, e'\\\\' -- b\nSELECT de'x' -- c";
+        let expected = vec![
+            ("-- b", CommentKind::Line),
+            ("-- c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::PostgreSql)));
+        let expected = vec![
+            ("-- no' AS a, e'\\\\' -- b", CommentKind::Line),
+            ("-- c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::Standard)));
+    }
+
+    #[test]
+    fn mysql() {
+        let input = "SELECT 1 # one\nSELECT 2--3\nSELECT 'a\\' -- b' -- c\n--\n-- d";
+        let expected = vec![
+            ("# one", CommentKind::Line),
+            ("-- c", CommentKind::Line),
+            ("--", CommentKind::Line),
+            ("-- d", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::MySql)));
+        let expected = vec![
+            ("--3", CommentKind::Line),
+            ("-- b' -- c", CommentKind::Line),
+            ("--", CommentKind::Line),
+            ("-- d", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::Standard)));
+    }
+
+    #[test]
+    fn sqlite_brackets() {
+        let input = "SELECT [a -- b] -- c";
+        let expected = vec![
+            ("-- c", CommentKind::Line)
+        ];
+        assert_eq!(expected, comment_texts(input, find_comments(input, SqlDialect::Sqlite)));
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::{self, Read, Write};
+use std::str;
+use super::filter::Filter;
+use super::{CommentMatch, Location, Scan, Error, ErrorKind, StreamError};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One scanner in the pipeline, holds back text that might still be removed
+pub struct Stage {
+    scanner: Box<dyn Scan>,
+    /// decides which matches are removed, all are if `None`
+    filter: Option<Filter>,
+    /// text not yet passed on, starting at `location`
+    buffer: String,
+    location: Location
+}
+
+impl Stage {
+    pub fn new(scanner: Box<dyn Scan>, filter: Option<Filter>) -> Self {
+        Stage {
+            scanner,
+            filter,
+            buffer: String::new(),
+            location: Location::start()
+        }
+    }
+
+    fn error(&self, kind: ErrorKind, offset: usize) -> Error {
+        Error {
+            kind,
+            location: self.location.advance(&self.buffer[..offset - self.location.offset])
+        }
+    }
+
+    /// Feed the next piece of text, everything that will be kept for sure is appended to `out`
+    fn feed(&mut self, text: &str, out: &mut String) -> Result<(), Error> {
+        let offset = self.location.offset + self.buffer.len();
+        self.buffer.push_str(text);
+        for (i, c) in text.char_indices() {
+            if let Err(e) = self.scanner.step(Some(c), offset + i) {
+                return Err(self.error(e.kind, e.offset));
+            }
+        }
+        self.flush(out);
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut String) -> Result<(), Error> {
+        let end = self.location.offset + self.buffer.len();
+        while !self.scanner.is_finished() {
+            if let Err(e) = self.scanner.step(None, end) {
+                return Err(self.error(e.kind, e.offset));
+            }
+        }
+        self.flush(out);
+        Ok(())
+    }
+
+    fn flush(&mut self, out: &mut String) {
+        for m in self.scanner.take_matches() {
+            self.pass_on(m.from, out);
+            let removed = match self.filter {
+                Some(ref mut filter) => filter.comment(m, &self.buffer[..m.to - self.location.offset]).is_some(),
+                None => true
+            };
+            if removed {
+                self.drop_until(m);
+            } else {
+                self.keep_until(m, out);
+            }
+        }
+        let safe = self.scanner.pending()
+            .unwrap_or(self.location.offset + self.buffer.len());
+        self.pass_on(safe, out);
+    }
+
+    /// Pass on text that is not a comment
+    fn pass_on(&mut self, until: usize, out: &mut String) {
+        let end = until - self.location.offset;
+        if let Some(ref mut filter) = self.filter {
+            filter.text(&self.buffer[..end]);
+        }
+        out.push_str(&self.buffer[..end]);
+        self.advance(end);
+    }
+
+    fn keep_until(&mut self, m: CommentMatch, out: &mut String) {
+        let end = m.to - self.location.offset;
+        out.push_str(&self.buffer[..end]);
+        self.advance(end);
+    }
+
+    fn drop_until(&mut self, m: CommentMatch) {
+        let end = m.to - self.location.offset;
+        self.advance(end);
+    }
+
+    fn advance(&mut self, end: usize) {
+        self.location = self.location.advance(&self.buffer[..end]);
+        self.buffer.drain(..end);
+    }
+}
+
+/// Run the input through all stages in order and write what is left
+pub fn strip<R: Read, W: Write>(mut stages: Vec<Stage>, mut reader: R, mut writer: W)
+    -> Result<(), StreamError> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    /* bytes of a char split between two reads */
+    let mut partial = Vec::new();
+    let mut location = Location::start();
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(StreamError::Read(e))
+        };
+        if read == 0 {
+            break;
+        }
+        partial.extend_from_slice(&chunk[..read]);
+        let valid = match str::from_utf8(&partial) {
+            Ok(_) => partial.len(),
+            Err(e) => match e.error_len() {
+                None => e.valid_up_to(),
+                Some(_) => {
+                    let valid = str::from_utf8(&partial[..e.valid_up_to()]).unwrap_or("");
+                    return Err(StreamError::Strip(Error {
+                        kind: ErrorKind::InvalidUtf8,
+                        location: location.advance(valid)
+                    }));
+                }
+            }
+        };
+        let text = str::from_utf8(&partial[..valid]).unwrap_or("").to_owned();
+        partial.drain(..valid);
+        location = location.advance(&text);
+        let out = run_stages(&mut stages, text, false)?;
+        writer.write_all(out.as_bytes()).map_err(StreamError::Write)?;
+    }
+    if !partial.is_empty() {
+        return Err(StreamError::Strip(Error {
+            kind: ErrorKind::InvalidUtf8,
+            location
+        }));
+    }
+    let out = run_stages(&mut stages, String::new(), true)?;
+    writer.write_all(out.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(StreamError::Write)
+}
+
+fn run_stages(stages: &mut [Stage], text: String, finish: bool) -> Result<String, Error> {
+    let mut text = text;
+    for stage in stages.iter_mut() {
+        let mut out = String::new();
+        stage.feed(&text, &mut out)?;
+        if finish {
+            stage.finish(&mut out)?;
+        }
+        text = out;
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{CommentStyle, Stripper, StyleSpec};
+    use super::super::{Error, ErrorKind, Location, StreamError};
+    use std::io::{self, Read};
+
+    /// Reader handing out at most `n` bytes per read
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn stream(stripper: &Stripper, input: &str, n: usize) -> Result<String, StreamError> {
+        let mut out = Vec::new();
+        stripper.strip_stream(Trickle(input.as_bytes(), n), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn same_as_strip() {
+        let inputs = [
+            (CommentStyle::C, "#include <a.h>\n/* Grüße\n */\n\n\nint a; // ä\nchar *s = \"/*ö*/\";\n"),
+            (CommentStyle::Shell, "#!/bin/sh # x\n# 日本\n\n\n\necho '#' # y\n"),
+            (CommentStyle::XML, "<a><!-- ü --><!-- b --></a>\n\n\n<b c=\"<!-- -->\"/><!--x-->"),
+            (CommentStyle::Rust, "/// doc\nfn f<'a>() { /* a /* b */ */ r#\"//\"#; }\n"),
+            (CommentStyle::Python, "#!/bin/python\n\"\"\"doc\"\"\"\nx = '#' # c\ndef f():\n    '''d'''  # e\n"),
+            (CommentStyle::Scss, "a { b: url(//x) } // c\n/* d */\n\n\ne { f: '//' }"),
+            (CommentStyle::JavaScript, "const a = <p>it's // x</p>; // b\nlet r = /\\/\\*/g, t = `${ `//` }`; /* ö */\n\n\n"),
+            (CommentStyle::Custom(StyleSpec::from_toml("name = 'lua'\nline = ['--']\nblock = [['--[[', ']]']]\n\
+                                                        strings = ['\"']\nraw_strings = [['[[', ']]']]").unwrap()),
+             "-- a\nx = 1 --[[ b\n]] - 2 --[ c\ns = \"--\" .. [[--]]\n\n\n-")
+        ];
+        for &(ref style, input) in inputs.iter() {
+            for &blanks in [true, false].iter() {
+                let stripper = Stripper::new(style.clone())
+                    .remove_blanks(blanks)
+                    .strip_docstrings(blanks)
+                    .keep_header(blanks);
+                let expected = stripper.strip(input).unwrap();
+                for n in 1..8 {
+                    assert_eq!(expected, stream(&stripper, input, n).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let stripper = Stripper::new(CommentStyle::C);
+        let expected = Error {
+            kind: ErrorKind::UnterminatedComment,
+            location: Location { offset: 7, line: 2, column: 3 }
+        };
+        match stream(&stripper, "ab;\nä /* 🦀", 2) {
+            Err(StreamError::Strip(e)) => assert_eq!(expected, e),
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let stripper = Stripper::new(CommentStyle::Shell);
+        let mut out = Vec::new();
+        let input = b"ok\n\xc3\xa4 \xff";
+        match stripper.strip_stream(Trickle(input, 1), &mut out) {
+            Err(StreamError::Strip(e)) => assert_eq!(Location { offset: 6, line: 2, column: 3 }, e.location),
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+}
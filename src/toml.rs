@@ -0,0 +1,228 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Normal,
+    Comment,
+    /// one `"`, a basic string or the start of `""` or `"""`
+    OneDoubleQuote,
+    /// `""`, an empty string unless a third one follows
+    TwoDoubleQuotes,
+    BasicString,
+    BasicStringEscaped,
+    MultilineBasicString,
+    MultilineBasicStringEscaped,
+    /// the number of `"` read in a multiline basic string, three and up to two more end it
+    MultilineBasicStringQuotes(usize),
+    OneSingleQuote,
+    TwoSingleQuotes,
+    LiteralString,
+    MultilineLiteralString,
+    MultilineLiteralStringQuotes(usize),
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::Normal
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentStarts,
+    CommentEnds
+}
+
+fn normal(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '#'     => (ParseState::Comment, ParseAction::CommentStarts),
+        '"'     => (ParseState::OneDoubleQuote, ParseAction::Nothing),
+        '\''    => (ParseState::OneSingleQuote, ParseAction::Nothing),
+        _       => (ParseState::Normal, ParseAction::Nothing)
+    }
+}
+
+fn basic_string(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '"'     => (ParseState::Normal, ParseAction::Nothing),
+        '\\'    => (ParseState::BasicStringEscaped, ParseAction::Nothing),
+        /* strings cannot span lines, do not hide the rest of the file */
+        '\n'    => (ParseState::Normal, ParseAction::Nothing),
+        _       => (ParseState::BasicString, ParseAction::Nothing)
+    }
+}
+
+fn multiline_basic_string(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '"'     => (ParseState::MultilineBasicStringQuotes(1), ParseAction::Nothing),
+        '\\'    => (ParseState::MultilineBasicStringEscaped, ParseAction::Nothing),
+        _       => (ParseState::MultilineBasicString, ParseAction::Nothing)
+    }
+}
+
+fn literal_string(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\'' | '\n' => (ParseState::Normal, ParseAction::Nothing),
+        _       => (ParseState::LiteralString, ParseAction::Nothing)
+    }
+}
+
+fn multiline_literal_string(c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\''    => (ParseState::MultilineLiteralStringQuotes(1), ParseAction::Nothing),
+        _       => (ParseState::MultilineLiteralString, ParseAction::Nothing)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::Normal => normal(c),
+            ParseState::Comment => match c {
+                '\n'    => (ParseState::Normal, ParseAction::CommentEnds),
+                _       => (ParseState::Comment, ParseAction::Nothing)
+            },
+            ParseState::OneDoubleQuote => match c {
+                '"'     => (ParseState::TwoDoubleQuotes, ParseAction::Nothing),
+                _       => basic_string(c)
+            },
+            ParseState::TwoDoubleQuotes => match c {
+                '"'     => (ParseState::MultilineBasicString, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::BasicString => basic_string(c),
+            ParseState::BasicStringEscaped =>
+                (ParseState::BasicString, ParseAction::Nothing),
+            ParseState::MultilineBasicString => multiline_basic_string(c),
+            /* also a backslash before a line break, which trims the whitespace after it */
+            ParseState::MultilineBasicStringEscaped =>
+                (ParseState::MultilineBasicString, ParseAction::Nothing),
+            ParseState::MultilineBasicStringQuotes(n) => match c {
+                '"' if n < 5 => (ParseState::MultilineBasicStringQuotes(n + 1), ParseAction::Nothing),
+                _ if n >= 3 => normal(c),
+                _       => multiline_basic_string(c)
+            },
+            ParseState::OneSingleQuote => match c {
+                '\''    => (ParseState::TwoSingleQuotes, ParseAction::Nothing),
+                _       => literal_string(c)
+            },
+            ParseState::TwoSingleQuotes => match c {
+                '\''    => (ParseState::MultilineLiteralString, ParseAction::Nothing),
+                _       => normal(c)
+            },
+            ParseState::LiteralString => literal_string(c),
+            ParseState::MultilineLiteralString => multiline_literal_string(c),
+            ParseState::MultilineLiteralStringQuotes(n) => match c {
+                '\'' if n < 5 => (ParseState::MultilineLiteralStringQuotes(n + 1), ParseAction::Nothing),
+                _ if n >= 3 => normal(c),
+                _       => multiline_literal_string(c)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::Comment => (ParseState::End, ParseAction::CommentEnds),
+            _ => (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentState {
+    NotInComment,
+    InComment(usize)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentStarts => {
+            comment_state = CommentState::InComment(position);
+        },
+        ParseAction::CommentEnds => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    matches.push(CommentMatch { from, to: position, kind: CommentKind::Line });
+                    comment_state = CommentState::NotInComment;
+                },
+                CommentState::NotInComment => {
+                    return Err(ScanError::new(ErrorKind::Parser("toml style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition, do_action)
+}
+
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_comments() {
+        let input = "# one\n[table] # two\nkey = 1 #three";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 5, kind: CommentKind::Line },
+            CommentMatch { from: 14, to: 19, kind: CommentKind::Line },
+            CommentMatch { from: 28, to: 34, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn no_comment_in_strings() {
+        let input = "a = \"#\\\"#\" # x\nb = '#\\' # y\n\"#key\" = '' # z\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 11, to: 14, kind: CommentKind::Line },
+            CommentMatch { from: 24, to: 27, kind: CommentKind::Line },
+            CommentMatch { from: 40, to: 43, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn no_comment_in_multiline_strings() {
+        let input = "a = \"\"\"\n# not \\\"\"\" # still not\n\"\"\" # x\n\
+                     b = '''\n# not '' ''' # y\n\
+                     c = \"\"\"quoted \"\"\"\"\" # z\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 35, to: 38, kind: CommentKind::Line },
+            CommentMatch { from: 60, to: 63, kind: CommentKind::Line },
+            CommentMatch { from: 84, to: 87, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+}
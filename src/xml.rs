@@ -1,21 +1,41 @@
-use super::{CommentMatch, Start, End, find_comments_impl};
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// Where a comment or markup declaration was found, so the scanner knows where to return
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// text content between tags
+    Content,
+    /// inside a start or end tag, between `<` and `>`
+    Tag,
+    /// internal subset of a DOCTYPE declaration, between `[` and `]`
+    Subset
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ParseState {
     Start,
     Normal,
-    CommentStartBracket,
-    CommentStartExcl,
-    CommentStartMinus1,
-    CommentStartMinus2,
-    Comment,
-    CommentEndMinus1,
-    CommentEndMinus2,
-    CommentEndBracket,
-    StringDoubleQuotes,
-    StringDoubleQuotesEscaped,
-    StringSingleQuotes,
-    StringSingleQuotesEscaped,
+    InTag,
+    AttributeValue(char),
+    Subset,
+    CommentStartBracket(Context),
+    CommentStartExcl(Context),
+    CommentStartMinus1(Context),
+    CommentStartMinus2(Context),
+    Comment(Context),
+    CommentEndMinus1(Context),
+    CommentEndMinus2(Context),
+    CommentEndBracket(Context),
+    /// chars of `[CDATA[` matched so far after `<!`
+    CDataStart(usize),
+    CData,
+    CDataEndBracket1,
+    CDataEndBracket2,
+    ProcessingInstruction(Context),
+    ProcessingInstructionQuestion(Context),
+    /// `<!DOCTYPE ...>`, `<!ENTITY ...>` and the like
+    Declaration(Context),
+    DeclarationQuotes(Context, char),
     End
 }
 
@@ -38,86 +58,148 @@ enum ParseAction {
     CommentConfirmed,
     CommentDismissed,
     CommentEnds,
-    CommentsEndsAndCommentOrTagStarts
+    CommentsEndsAndCommentOrTagStarts,
+    /// `--` inside a comment, the current char follows it
+    DoubleHyphen,
+    CommentUnterminated
+}
+
+const CDATA_START: &[u8] = b"[CDATA[";
+
+/// Transition for a char outside of any comment or markup
+fn outside(context: Context, c: char) -> (ParseState, ParseAction) {
+    match (context, c) {
+        (_, '<')                => (ParseState::CommentStartBracket(context), ParseAction::CommentOrTagStarts),
+        (Context::Content, _)   => (ParseState::Normal, ParseAction::Nothing),
+        (Context::Tag, '>')     => (ParseState::Normal, ParseAction::Nothing),
+        (Context::Tag, '"') | (Context::Tag, '\'')
+                                => (ParseState::AttributeValue(c), ParseAction::Nothing),
+        (Context::Tag, _)       => (ParseState::InTag, ParseAction::Nothing),
+        (Context::Subset, ']')  => (ParseState::Declaration(Context::Content), ParseAction::Nothing),
+        (Context::Subset, _)    => (ParseState::Subset, ParseAction::Nothing)
+    }
+}
+
+fn after(context: Context) -> ParseState {
+    match context {
+        Context::Content => ParseState::Normal,
+        Context::Tag => ParseState::InTag,
+        Context::Subset => ParseState::Subset
+    }
 }
 
 fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
     match current_char {
         Some(c) => match from {
-            ParseState::Start => match c {
-                '<'     => (ParseState::CommentStartBracket, ParseAction::CommentOrTagStarts),
-                '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
-                '\''    => (ParseState::StringSingleQuotes, ParseAction::Nothing),
-                _       => (ParseState::Normal, ParseAction::Nothing)
+            ParseState::Start | ParseState::Normal =>
+                outside(Context::Content, c),
+            ParseState::InTag =>
+                outside(Context::Tag, c),
+            ParseState::AttributeValue(quote) =>
+                if c == quote {
+                    (ParseState::InTag, ParseAction::Nothing)
+                } else {
+                    (ParseState::AttributeValue(quote), ParseAction::Nothing)
+                },
+            ParseState::Subset =>
+                outside(Context::Subset, c),
+            ParseState::CommentStartBracket(context) => match c {
+                '!'     => (ParseState::CommentStartExcl(context), ParseAction::Nothing),
+                '?'     => (ParseState::ProcessingInstruction(context), ParseAction::CommentDismissed),
+                '/' | ':' | '_' if context == Context::Content
+                        => (ParseState::InTag, ParseAction::CommentDismissed),
+                _ if context == Context::Content && c.is_alphabetic()
+                        => (ParseState::InTag, ParseAction::CommentDismissed),
+                _       => (after(context), ParseAction::CommentDismissed)
             },
-            ParseState::Normal => match c {
-                '<'     => (ParseState::CommentStartBracket, ParseAction::CommentOrTagStarts),
-                '"'     => (ParseState::StringDoubleQuotes, ParseAction::Nothing),
-                '\''    => (ParseState::StringSingleQuotes, ParseAction::Nothing),
-                _       => (ParseState::Normal, ParseAction::Nothing)
+            ParseState::CommentStartExcl(context) => match c {
+                '-'     => (ParseState::CommentStartMinus1(context), ParseAction::Nothing),
+                '[' if context == Context::Content
+                        => (ParseState::CDataStart(1), ParseAction::CommentDismissed),
+                _ if context == Context::Tag
+                        => (ParseState::InTag, ParseAction::CommentDismissed),
+                _       => (ParseState::Declaration(context), ParseAction::CommentDismissed)
             },
-            ParseState::CommentStartBracket => match c {
-                '!'     => (ParseState::CommentStartExcl, ParseAction::Nothing),
-                _       => (ParseState::Normal, ParseAction::CommentDismissed)
+            ParseState::CommentStartMinus1(context) => match c {
+                '-'     => (ParseState::CommentStartMinus2(context), ParseAction::CommentConfirmed),
+                _       => (after(context), ParseAction::CommentDismissed)
             },
-            ParseState::CommentStartExcl => match c {
-                '-'     => (ParseState::CommentStartMinus1, ParseAction::Nothing),
-                _       => (ParseState::Normal, ParseAction::CommentDismissed)
+            ParseState::CommentStartMinus2(context) => match c {
+                '-'     => (ParseState::CommentEndMinus1(context), ParseAction::Nothing),
+                _       => (ParseState::Comment(context), ParseAction::Nothing)
             },
-            ParseState::CommentStartMinus1 => match c {
-                '-'     => (ParseState::CommentStartMinus2, ParseAction::CommentConfirmed),
-                _       => (ParseState::Normal, ParseAction::CommentDismissed)
+            ParseState::Comment(context) => match c {
+                '-'     => (ParseState::CommentEndMinus1(context), ParseAction::Nothing),
+                _       => (ParseState::Comment(context), ParseAction::Nothing)
             },
-            ParseState::CommentStartMinus2 => match c {
-                '-'     => (ParseState::CommentEndMinus1, ParseAction::Nothing),
-                _       => (ParseState::Comment, ParseAction::Nothing)
+            ParseState::CommentEndMinus1(context) => match c {
+                '-'     => (ParseState::CommentEndMinus2(context), ParseAction::Nothing),
+                _       => (ParseState::Comment(context), ParseAction::Nothing)
             },
-            ParseState::Comment => match c {
-                '-'     => (ParseState::CommentEndMinus1, ParseAction::Nothing),
-                _       => (ParseState::Comment, ParseAction::Nothing)
+            ParseState::CommentEndMinus2(context) => match c {
+                '>'     => (ParseState::CommentEndBracket(context), ParseAction::Nothing),
+                _       => (ParseState::Comment(context), ParseAction::DoubleHyphen)
             },
-            ParseState::CommentEndMinus1 => match c {
-                '-'     => (ParseState::CommentEndMinus2, ParseAction::Nothing),
-                _       => (ParseState::Comment, ParseAction::Nothing)
+            ParseState::CommentEndBracket(context) => match outside(context, c) {
+                (state, ParseAction::CommentOrTagStarts) => (state, ParseAction::CommentsEndsAndCommentOrTagStarts),
+                (state, _) => (state, ParseAction::CommentEnds)
             },
-            ParseState::CommentEndMinus2 => match c {
-                '>'     => (ParseState::CommentEndBracket, ParseAction::Nothing),
-                '-'     => (ParseState::CommentEndMinus2, ParseAction::Nothing),
-                _       => (ParseState::Comment, ParseAction::Nothing)
+            ParseState::CDataStart(matched) => match CDATA_START.get(matched) {
+                Some(&b) if b as char == c && matched + 1 == CDATA_START.len()
+                        => (ParseState::CData, ParseAction::Nothing),
+                Some(&b) if b as char == c
+                        => (ParseState::CDataStart(matched + 1), ParseAction::Nothing),
+                _       => outside(Context::Content, c)
             },
-            ParseState::CommentEndBracket => match c {
-                '<'     => (ParseState::CommentStartBracket, ParseAction::CommentsEndsAndCommentOrTagStarts),
-                '"'     => (ParseState::StringDoubleQuotes, ParseAction::CommentEnds),
-                '\''    => (ParseState::StringSingleQuotes, ParseAction::CommentEnds),
-                _       => (ParseState::Normal, ParseAction::CommentEnds)
+            ParseState::CData => match c {
+                ']'     => (ParseState::CDataEndBracket1, ParseAction::Nothing),
+                _       => (ParseState::CData, ParseAction::Nothing)
             },
-            ParseState::StringDoubleQuotes => match c {
-                '"'     => (ParseState::Normal, ParseAction::Nothing),
-                '\\'    => (ParseState::StringDoubleQuotesEscaped, ParseAction::Nothing),
-                _       => (ParseState::StringDoubleQuotes, ParseAction::Nothing)
+            ParseState::CDataEndBracket1 => match c {
+                ']'     => (ParseState::CDataEndBracket2, ParseAction::Nothing),
+                _       => (ParseState::CData, ParseAction::Nothing)
             },
-            ParseState::StringDoubleQuotesEscaped =>
-                (ParseState::StringDoubleQuotes, ParseAction::Nothing),
-            ParseState::StringSingleQuotes => match c {
-                '\''     => (ParseState::Normal, ParseAction::Nothing),
-                '\\'    => (ParseState::StringSingleQuotesEscaped, ParseAction::Nothing),
-                _       => (ParseState::StringSingleQuotes, ParseAction::Nothing)
+            ParseState::CDataEndBracket2 => match c {
+                '>'     => (ParseState::Normal, ParseAction::Nothing),
+                ']'     => (ParseState::CDataEndBracket2, ParseAction::Nothing),
+                _       => (ParseState::CData, ParseAction::Nothing)
             },
-            ParseState::StringSingleQuotesEscaped =>
-                (ParseState::StringSingleQuotes, ParseAction::Nothing),
+            ParseState::ProcessingInstruction(context) => match c {
+                '?'     => (ParseState::ProcessingInstructionQuestion(context), ParseAction::Nothing),
+                _       => (ParseState::ProcessingInstruction(context), ParseAction::Nothing)
+            },
+            ParseState::ProcessingInstructionQuestion(context) => match c {
+                '>'     => (after(context), ParseAction::Nothing),
+                '?'     => (ParseState::ProcessingInstructionQuestion(context), ParseAction::Nothing),
+                _       => (ParseState::ProcessingInstruction(context), ParseAction::Nothing)
+            },
+            ParseState::Declaration(context) => match c {
+                '>'     => (after(context), ParseAction::Nothing),
+                '[' if context == Context::Content
+                        => (ParseState::Subset, ParseAction::Nothing),
+                '"' | '\''
+                        => (ParseState::DeclarationQuotes(context, c), ParseAction::Nothing),
+                _       => (ParseState::Declaration(context), ParseAction::Nothing)
+            },
+            ParseState::DeclarationQuotes(context, quote) =>
+                if c == quote {
+                    (ParseState::Declaration(context), ParseAction::Nothing)
+                } else {
+                    (ParseState::DeclarationQuotes(context, quote), ParseAction::Nothing)
+                },
             ParseState::End =>
                 (ParseState::End, ParseAction::Nothing)
         },
         None => match from {
-            ParseState::CommentStartBracket => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentStartExcl    => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentStartMinus1  => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentStartMinus2  => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::Comment             => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentEndMinus1    => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentEndMinus2    => (ParseState::End, ParseAction::CommentDismissed),
-            ParseState::CommentEndBracket   => (ParseState::End, ParseAction::CommentEnds),
-            _                               => (ParseState::End, ParseAction::Nothing)
+            ParseState::CommentStartBracket(_) => (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::CommentStartExcl(_)    => (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::CommentStartMinus1(_)  => (ParseState::End, ParseAction::CommentDismissed),
+            ParseState::CommentStartMinus2(_)  => (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::Comment(_)             => (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::CommentEndMinus1(_)    => (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::CommentEndMinus2(_)    => (ParseState::End, ParseAction::CommentUnterminated),
+            ParseState::CommentEndBracket(_)   => (ParseState::End, ParseAction::CommentEnds),
+            _                                  => (ParseState::End, ParseAction::Nothing)
         }
     }
 }
@@ -135,9 +217,18 @@ impl Start for CommentState {
     }
 }
 
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::InCommentOrTag(from) | CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
 fn do_action(action: ParseAction, mut comment_state: CommentState, 
             position: usize, mut matches: Vec<CommentMatch>) 
-    -> Result<(CommentState, Vec<CommentMatch>), &'static str> {
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
     match action {
         ParseAction::Nothing => {},
         ParseAction::CommentOrTagStarts => {
@@ -149,7 +240,7 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
                     comment_state = CommentState::InComment(from);
                 },
                 _ => {
-                    return Err("xml style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("xml style"), position));
                 }
             }
         },
@@ -159,22 +250,35 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
         ParseAction::CommentEnds => {
             match comment_state {
                 CommentState::InComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                    matches.push(CommentMatch{from, to: position, kind: CommentKind::Block});
                     comment_state = CommentState::NotInComment;
                 },
                 _ => {
-                    return Err("xml style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("xml style"), position));
                 }
             }
         },
         ParseAction::CommentsEndsAndCommentOrTagStarts => {
             match comment_state {
                 CommentState::InComment(from) => {
-                    matches.push(CommentMatch{from: from, to: position});
+                    matches.push(CommentMatch{from, to: position, kind: CommentKind::Block});
                     comment_state = CommentState::InCommentOrTag(position);
                 },
                 _ => {
-                    return Err("xml style parser error");
+                    return Err(ScanError::new(ErrorKind::Parser("xml style"), position));
+                }
+            }
+        },
+        ParseAction::DoubleHyphen => {
+            return Err(ScanError::new(ErrorKind::DoubleHyphenInComment, position - 2));
+        },
+        ParseAction::CommentUnterminated => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    return Err(ScanError::new(ErrorKind::UnterminatedComment, from));
+                },
+                _ => {
+                    return Err(ScanError::new(ErrorKind::Parser("xml style"), position));
                 }
             }
         }
@@ -182,10 +286,14 @@ fn do_action(action: ParseAction, mut comment_state: CommentState,
     Ok((comment_state, matches))
 }
 
-pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, &'static str> {
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
     find_comments_impl(input, state_transition, do_action)
 }
 
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,7 +311,7 @@ mod tests {
     fn normal_comment() {
         let input = "<t /><!-- some comment -->\n<tag />";
         let expected = Ok(vec![
-            CommentMatch { from: 5, to: 26}
+            CommentMatch { from: 5, to: 26, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -213,8 +321,8 @@ mod tests {
     fn multiple_comments() {
         let input = "<t /><!-- some comment --><t></t><!-- another comment -->";
         let expected = Ok(vec![
-            CommentMatch { from: 5, to: 26 },
-            CommentMatch { from: 33, to: 57 }
+            CommentMatch { from: 5, to: 26, kind: CommentKind::Block },
+            CommentMatch { from: 33, to: 57, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -224,7 +332,7 @@ mod tests {
     fn comment_in_tag() {
         let input = "<tag <!-- comment -->></tag>";
         let expected = Ok(vec![
-            CommentMatch { from: 5, to: 21 }
+            CommentMatch { from: 5, to: 21, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -234,7 +342,28 @@ mod tests {
     fn multiline_comment() {
         let input = "<!--\nmulti\nline\ncomment\n-->";
         let expected = Ok(vec![
-            CommentMatch { from: 0, to: 27 }
+            CommentMatch { from: 0, to: 27, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn apostrophe_in_text() {
+        let input = "<a>it's</a><!-- c --><b>\"quoted</b><!-- d -->";
+        let expected = Ok(vec![
+            CommentMatch { from: 11, to: 21, kind: CommentKind::Block },
+            CommentMatch { from: 35, to: 45, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn quotes_only_in_attribute_values() {
+        let input = "<a title='it\"s <!-- x -->' b=\"\\\">'<!-- c --></a>";
+        let expected = Ok(vec![
+            CommentMatch { from: 34, to: 44, kind: CommentKind::Block }
         ]);
         let actual = find_comments(input);
         assert_eq!(expected, actual);
@@ -247,4 +376,64 @@ mod tests {
         let actual = find_comments(input);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn multibyte_offsets() {
+        let input = "<p>€</p><!-- ✓ -->";
+        let expected = Ok(vec![
+            CommentMatch { from: 10, to: 22, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let input = "<a/>\n<!-- comment --";
+        let expected = Err(ScanError::new(ErrorKind::UnterminatedComment, 5).locate(input));
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_comment_in_cdata() {
+        let input = "<a><![CDATA[ <!-- not a comment --> ]] ]]]><!-- c --></a>";
+        let expected = Ok(vec![
+            CommentMatch { from: 43, to: 53, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn no_comment_in_processing_instruction() {
+        let input = "<?xml version=\"1.0\"?><?pi <!-- x --> ?><!-- c -->";
+        let expected = Ok(vec![
+            CommentMatch { from: 39, to: 49, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn comments_in_doctype_subset() {
+        let input = "<!DOCTYPE a [\n<!-- c -->\n<!ENTITY e \"<!-- x -->\">\n<?pi ]> ?>\n]><a/>";
+        let expected = Ok(vec![
+            CommentMatch { from: 14, to: 24, kind: CommentKind::Block }
+        ]);
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn double_hyphen_in_comment() {
+        let input = "<a/>\n<!-- a -- b -->";
+        let expected = Err(ScanError::new(ErrorKind::DoubleHyphenInComment, 12).locate(input));
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+        let input = "<!-- a --->";
+        let expected = Err(ScanError::new(ErrorKind::DoubleHyphenInComment, 7).locate(input));
+        let actual = find_comments(input);
+        assert_eq!(expected, actual);
+    }
 }
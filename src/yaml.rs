@@ -0,0 +1,330 @@
+use super::{CommentKind, CommentMatch, Start, End, Pending, Scan, Scanner, Error, ErrorKind, ScanError, find_comments_impl};
+
+/// Where a quoted scalar or a comment returns to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// block context, with the column where the current node started
+    Block(usize),
+    /// inside `[ ]` or `{ }`, with the nesting depth
+    Flow(usize)
+}
+
+/// Columns count chars, an indentation is spaces only so this is exact where it matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    /// indentation of a new line, with the column
+    LineStart(usize),
+    /// after an indicator like `key: ` or `- ` where a node may start,
+    /// with the indentation a block scalar must exceed and the column
+    NodeStart(usize, usize),
+    /// `-` where a node may start, a sequence entry if whitespace follows
+    Dash(usize),
+    /// anchor or tag like `&name` or `!!str` before a node
+    Property(usize, usize),
+    /// plain scalar, with the column it started at in case it is a mapping key
+    Plain(usize),
+    PlainSpace(usize),
+    PlainColon(usize),
+    SingleQuotes(Context),
+    /// a `'` in single quotes, either the end or the first half of `''`
+    SingleQuotesMaybeEnd(Context),
+    DoubleQuotes(Context),
+    DoubleQuotesEscaped(Context),
+    /// `|` or `>` with its indicators, with the indentation the content must exceed
+    BlockHeader(usize),
+    BlockHeaderSpace(usize),
+    BlockHeaderComment(usize),
+    /// indentation of a line in a block scalar, with the column
+    BlockLineStart(usize, usize),
+    BlockContent(usize),
+    /// after `[`, `{` or `,` in flow context
+    FlowIndicator(usize),
+    FlowSpace(usize),
+    FlowPlain(usize),
+    Comment(Context),
+    End
+}
+
+impl Start for ParseState {
+    fn start() -> Self {
+        ParseState::LineStart(0)
+    }
+}
+
+impl End for ParseState {
+    fn end() -> Self {
+        ParseState::End
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseAction {
+    Nothing,
+    CommentStarts,
+    CommentEnds
+}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\r'
+}
+
+/// Where a node may start: quotes, block scalars, sequence entries and comments
+fn node_start(parent: usize, column: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\n'    => (ParseState::LineStart(0), ParseAction::Nothing),
+        '#'     => (ParseState::Comment(Context::Block(parent)), ParseAction::CommentStarts),
+        '|' | '>' => (ParseState::BlockHeader(parent), ParseAction::Nothing),
+        '-'     => (ParseState::Dash(column), ParseAction::Nothing),
+        '&' | '!' => (ParseState::Property(parent, column), ParseAction::Nothing),
+        '\''    => (ParseState::SingleQuotes(Context::Block(column)), ParseAction::Nothing),
+        '"'     => (ParseState::DoubleQuotes(Context::Block(column)), ParseAction::Nothing),
+        '[' | '{' => (ParseState::FlowIndicator(1), ParseAction::Nothing),
+        c if is_space(c) => (ParseState::NodeStart(parent, column + 1), ParseAction::Nothing),
+        _       => (ParseState::Plain(column), ParseAction::Nothing)
+    }
+}
+
+/// Inside a plain scalar, `#` is only a comment after whitespace
+fn plain(key: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '\n'    => (ParseState::LineStart(0), ParseAction::Nothing),
+        ':'     => (ParseState::PlainColon(key), ParseAction::Nothing),
+        c if is_space(c) => (ParseState::PlainSpace(key), ParseAction::Nothing),
+        _       => (ParseState::Plain(key), ParseAction::Nothing)
+    }
+}
+
+fn flow(depth: usize, c: char) -> (ParseState, ParseAction) {
+    match c {
+        '[' | '{' => (ParseState::FlowIndicator(depth + 1), ParseAction::Nothing),
+        ']' | '}' if depth == 1 => (ParseState::Plain(0), ParseAction::Nothing),
+        ']' | '}' => (ParseState::FlowPlain(depth - 1), ParseAction::Nothing),
+        ','     => (ParseState::FlowIndicator(depth), ParseAction::Nothing),
+        c if c.is_whitespace() => (ParseState::FlowSpace(depth), ParseAction::Nothing),
+        _       => (ParseState::FlowPlain(depth), ParseAction::Nothing)
+    }
+}
+
+fn after_quotes(context: Context) -> ParseState {
+    match context {
+        Context::Block(key) => ParseState::Plain(key),
+        Context::Flow(depth) => ParseState::FlowPlain(depth)
+    }
+}
+
+fn state_transition(from: ParseState, current_char: Option<char>) -> (ParseState, ParseAction) {
+    match current_char {
+        Some(c) => match from {
+            ParseState::LineStart(column) => node_start(column, column, c),
+            ParseState::NodeStart(parent, column) => node_start(parent, column, c),
+            ParseState::Dash(column) => match c {
+                '\n'    => (ParseState::LineStart(0), ParseAction::Nothing),
+                c if is_space(c) => (ParseState::NodeStart(column, column + 2), ParseAction::Nothing),
+                _       => plain(column, c)
+            },
+            ParseState::Property(parent, column) => match c {
+                '\n'    => (ParseState::LineStart(0), ParseAction::Nothing),
+                c if is_space(c) => (ParseState::NodeStart(parent, column), ParseAction::Nothing),
+                _       => (ParseState::Property(parent, column), ParseAction::Nothing)
+            },
+            ParseState::Plain(key) => plain(key, c),
+            ParseState::PlainSpace(key) => match c {
+                '#'     => (ParseState::Comment(Context::Block(key)), ParseAction::CommentStarts),
+                _       => plain(key, c)
+            },
+            ParseState::PlainColon(key) => match c {
+                c if is_space(c) => (ParseState::NodeStart(key, key + 1), ParseAction::Nothing),
+                _       => plain(key, c)
+            },
+            ParseState::SingleQuotes(context) => match c {
+                '\''    => (ParseState::SingleQuotesMaybeEnd(context), ParseAction::Nothing),
+                _       => (ParseState::SingleQuotes(context), ParseAction::Nothing)
+            },
+            ParseState::SingleQuotesMaybeEnd(context) => match c {
+                '\''    => (ParseState::SingleQuotes(context), ParseAction::Nothing),
+                _       => state_transition(after_quotes(context), current_char)
+            },
+            ParseState::DoubleQuotes(context) => match c {
+                '"'     => (after_quotes(context), ParseAction::Nothing),
+                '\\'    => (ParseState::DoubleQuotesEscaped(context), ParseAction::Nothing),
+                _       => (ParseState::DoubleQuotes(context), ParseAction::Nothing)
+            },
+            ParseState::DoubleQuotesEscaped(context) =>
+                (ParseState::DoubleQuotes(context), ParseAction::Nothing),
+            ParseState::BlockHeader(parent) => match c {
+                '\n'    => (ParseState::BlockLineStart(parent, 0), ParseAction::Nothing),
+                '+' | '-' => (ParseState::BlockHeader(parent), ParseAction::Nothing),
+                c if c.is_ascii_digit() => (ParseState::BlockHeader(parent), ParseAction::Nothing),
+                c if is_space(c) => (ParseState::BlockHeaderSpace(parent), ParseAction::Nothing),
+                _       => plain(parent, c)
+            },
+            ParseState::BlockHeaderSpace(parent) => match c {
+                '\n'    => (ParseState::BlockLineStart(parent, 0), ParseAction::Nothing),
+                '#'     => (ParseState::BlockHeaderComment(parent), ParseAction::CommentStarts),
+                c if is_space(c) => (ParseState::BlockHeaderSpace(parent), ParseAction::Nothing),
+                _       => plain(parent, c)
+            },
+            ParseState::BlockHeaderComment(parent) => match c {
+                '\n'    => (ParseState::BlockLineStart(parent, 0), ParseAction::CommentEnds),
+                _       => (ParseState::BlockHeaderComment(parent), ParseAction::Nothing)
+            },
+            /* blank lines and lines indented more than the parent are content */
+            ParseState::BlockLineStart(parent, column) => match c {
+                '\n'    => (ParseState::BlockLineStart(parent, 0), ParseAction::Nothing),
+                ' '     => (ParseState::BlockLineStart(parent, column + 1), ParseAction::Nothing),
+                '\r'    => (ParseState::BlockLineStart(parent, column), ParseAction::Nothing),
+                _ if column > parent => (ParseState::BlockContent(parent), ParseAction::Nothing),
+                _       => node_start(column, column, c)
+            },
+            ParseState::BlockContent(parent) => match c {
+                '\n'    => (ParseState::BlockLineStart(parent, 0), ParseAction::Nothing),
+                _       => (ParseState::BlockContent(parent), ParseAction::Nothing)
+            },
+            ParseState::FlowIndicator(depth) => match c {
+                '\''    => (ParseState::SingleQuotes(Context::Flow(depth)), ParseAction::Nothing),
+                '"'     => (ParseState::DoubleQuotes(Context::Flow(depth)), ParseAction::Nothing),
+                _       => flow(depth, c)
+            },
+            ParseState::FlowSpace(depth) => match c {
+                '#'     => (ParseState::Comment(Context::Flow(depth)), ParseAction::CommentStarts),
+                '\''    => (ParseState::SingleQuotes(Context::Flow(depth)), ParseAction::Nothing),
+                '"'     => (ParseState::DoubleQuotes(Context::Flow(depth)), ParseAction::Nothing),
+                _       => flow(depth, c)
+            },
+            ParseState::FlowPlain(depth) => flow(depth, c),
+            ParseState::Comment(context) => match (c, context) {
+                ('\n', Context::Block(_)) => (ParseState::LineStart(0), ParseAction::CommentEnds),
+                ('\n', Context::Flow(depth)) => (ParseState::FlowSpace(depth), ParseAction::CommentEnds),
+                _       => (ParseState::Comment(context), ParseAction::Nothing)
+            },
+            ParseState::End =>
+                (ParseState::End, ParseAction::Nothing)
+        },
+        None => match from {
+            ParseState::Comment(_) | ParseState::BlockHeaderComment(_) =>
+                (ParseState::End, ParseAction::CommentEnds),
+            _ => (ParseState::End, ParseAction::Nothing)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentState {
+    NotInComment,
+    InComment(usize)
+}
+
+impl Start for CommentState {
+    fn start() -> Self {
+        CommentState::NotInComment
+    }
+}
+
+impl Pending for CommentState {
+    fn pending(&self) -> Option<usize> {
+        match *self {
+            CommentState::NotInComment => None,
+            CommentState::InComment(from) => Some(from)
+        }
+    }
+}
+
+fn do_action(action: ParseAction, mut comment_state: CommentState,
+            position: usize, mut matches: Vec<CommentMatch>)
+    -> Result<(CommentState, Vec<CommentMatch>), ScanError> {
+    match action {
+        ParseAction::Nothing => {},
+        ParseAction::CommentStarts => {
+            comment_state = CommentState::InComment(position);
+        },
+        ParseAction::CommentEnds => {
+            match comment_state {
+                CommentState::InComment(from) => {
+                    matches.push(CommentMatch { from, to: position, kind: CommentKind::Line });
+                    comment_state = CommentState::NotInComment;
+                },
+                CommentState::NotInComment => {
+                    return Err(ScanError::new(ErrorKind::Parser("yaml style"), position));
+                }
+            }
+        }
+    }
+    Ok((comment_state, matches))
+}
+
+pub fn find_comments(input: &str) -> Result<Vec<CommentMatch>, Error> {
+    find_comments_impl(input, state_transition, do_action)
+}
+
+pub fn scanner() -> Box<dyn Scan> {
+    Box::new(Scanner::new(state_transition, do_action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::CommentStyle;
+
+    #[test]
+    fn line_comments() {
+        let input = "# one\nkey: value # two\n  # three\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 0, to: 5, kind: CommentKind::Line },
+            CommentMatch { from: 17, to: 22, kind: CommentKind::Line },
+            CommentMatch { from: 25, to: 32, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn hash_needs_whitespace() {
+        let input = "url: http://x#frag\ncolor: a#b #c\nlist: [a#b, c] #d\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 30, to: 32, kind: CommentKind::Line },
+            CommentMatch { from: 48, to: 50, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn quoted_scalars() {
+        let input = "a: 'it''s # not' # x\nb: \"\\\" # not\" # y\nc: it's # z\nd: ['#', \"#\"] # w\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 17, to: 20, kind: CommentKind::Line },
+            CommentMatch { from: 35, to: 38, kind: CommentKind::Line },
+            CommentMatch { from: 47, to: 50, kind: CommentKind::Line },
+            CommentMatch { from: 65, to: 68, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn block_scalars() {
+        let input = "script: | # header\n  echo a # not\n\n  # not either\nfolded: >-\n  b # no\n# yes\n\
+                     - run: |\n    c # no\n  name: d # too\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 10, to: 18, kind: CommentKind::Line },
+            CommentMatch { from: 70, to: 75, kind: CommentKind::Line },
+            CommentMatch { from: 106, to: 111, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+
+    #[test]
+    fn tool_words_are_not_directives() {
+        let input = "#type:x\nkind: a #image:nginx\n# yamllint disable\n";
+        let kinds: Vec<_> = super::super::find_comments(input, &CommentStyle::Yaml).unwrap().iter()
+            .map(|m| m.kind)
+            .collect();
+        assert_eq!(vec![CommentKind::Line, CommentKind::Line, CommentKind::Directive], kinds);
+    }
+
+    #[test]
+    fn pipe_in_plain_scalar() {
+        let input = "cmd: echo a | grep b\n  # c\n";
+        let expected = Ok(vec![
+            CommentMatch { from: 23, to: 26, kind: CommentKind::Line }
+        ]);
+        assert_eq!(expected, find_comments(input));
+    }
+}
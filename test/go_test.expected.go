@@ -0,0 +1,7 @@
+package main
+import "fmt"
+func main() {
+	s := `raw // not a comment \` 
+	r := '"' 
+	fmt.Println(s, r, "// not either")
+}
@@ -0,0 +1,10 @@
+// Package main says hello
+package main
+
+import "fmt"
+
+func main() {
+	s := `raw // not a comment \` // a comment
+	r := '"' /* a block */
+	fmt.Println(s, r, "// not either")
+}
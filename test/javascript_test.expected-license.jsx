@@ -0,0 +1,18 @@
+#!/usr/bin/env node
+/*! widget v1.2.0 | MIT */
+// @ts-check
+import React from 'react'; 
+const slashes = /\/\*|\/\//g; 
+const ratio = width / 2  / scale;
+const url = `https://${host}/api // not a comment ${ `/* nested ${id} */` }`;
+export function Greeting({ name }) {
+  return (
+    <div className="greeting" data-x='//'>
+      {}
+      Hello, // this is text
+      <b>{name}</b>
+    </div>
+  );
+}
+/** @preserve keep me with --keep-license-comments */
+let count = 0; count++ / 2; 
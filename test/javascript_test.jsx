@@ -0,0 +1,25 @@
+#!/usr/bin/env node
+/*! widget v1.2.0 | MIT */
+/**
+ * Renders the greeting.
+ * @param {string} name
+ */
+// @ts-check
+import React from 'react'; // react
+
+const slashes = /\/\*|\/\//g; // matches comment starts
+const ratio = width / 2 /* half */ / scale;
+const url = `https://${host}/api // not a comment ${ `/* nested ${id} */` }`;
+
+export function Greeting({ name }) {
+  return (
+    <div className="greeting" data-x='//'>
+      {/* a JSX comment */}
+      Hello, // this is text
+      <b>{name}</b>
+    </div>
+  );
+}
+
+/** @preserve keep me with --keep-license-comments */
+let count = 0; count++ / 2; // done
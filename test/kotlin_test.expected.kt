@@ -0,0 +1,5 @@
+fun main() {
+    val s = """raw " // not a comment""" 
+    val c = '"' 
+    println("$s $c /* not either */")
+}
@@ -0,0 +1,6 @@
+/* Outer /* nested */ still a comment */
+fun main() {
+    val s = """raw " // not a comment""" // a comment
+    val c = '"' // another
+    println("$s $c /* not either */")
+}
@@ -0,0 +1,5 @@
+local s = "-- not \" a comment" 
+local t = [[
+-- nor is this
+]] 
+return s .. t
@@ -0,0 +1,8 @@
+-- a module
+--[[ with a
+block comment ]]
+local s = "-- not \" a comment" -- but this is
+local t = [[
+-- nor is this
+]] --[ and this is a line comment
+return s .. t
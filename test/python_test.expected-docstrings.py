@@ -0,0 +1,11 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+""""""
+import re  
+class Greeter:
+    ''''''
+    def greet(self, name):
+        """"""
+        
+        pattern = rb'#[0-9]+'
+        return f"Hello {name['#'] if isinstance(name, dict) else name}!"  
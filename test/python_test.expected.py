@@ -0,0 +1,11 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+"""Module docstring with a # hash."""
+import re  
+class Greeter:
+    '''Greets people.'''
+    def greet(self, name):
+        """Return a greeting # not a comment."""
+        
+        pattern = rb'#[0-9]+'
+        return f"Hello {name['#'] if isinstance(name, dict) else name}!"  
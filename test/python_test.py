@@ -0,0 +1,14 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+"""Module docstring with a # hash."""
+import re  # regular expressions
+
+
+class Greeter:
+    '''Greets people.'''
+
+    def greet(self, name):
+        """Return a greeting # not a comment."""
+        # build the message
+        pattern = rb'#[0-9]+'
+        return f"Hello {name['#'] if isinstance(name, dict) else name}!"  # done
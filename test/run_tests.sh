@@ -6,3 +6,72 @@ test -x $STRIP
 $STRIP --shell-style "test/shell_test.sh" | diff "test/shell_test.expected.sh" -
 $STRIP --xml-style "test/xml_test.xml" | diff "test/xml_test.expected.xml" -
 $STRIP --c-style "test/c_test.c" | diff "test/c_test.expected.c" -
+$STRIP --shell-style "test/utf8_test.sh" | diff "test/utf8_test.expected.sh" -
+$STRIP --xml-style "test/utf8_test.xml" | diff "test/utf8_test.expected.xml" -
+$STRIP --c-style "test/utf8_test.c" | diff "test/utf8_test.expected.c" -
+$STRIP --stream --c-style "test/c_test.c" | diff "test/c_test.expected.c" -
+$STRIP --stream --shell-style "test/utf8_test.sh" | diff "test/utf8_test.expected.sh" -
+$STRIP --stream --xml-style "test/utf8_test.xml" | diff "test/utf8_test.expected.xml" -
+$STRIP --rust-style "test/rust_test.rs" | diff "test/rust_test.expected.rs" -
+$STRIP --rust-style --keep-doc-comments "test/rust_test.rs" | diff "test/rust_test.expected-doc.rs" -
+$STRIP --python-style "test/python_test.py" | diff "test/python_test.expected.py" -
+$STRIP --python-style --strip-docstrings "test/python_test.py" | diff "test/python_test.expected-docstrings.py" -
+$STRIP --auto "test/rust_test.rs" | diff "test/rust_test.expected.rs" -
+$STRIP --auto "test/python_test.py" | diff "test/python_test.expected.py" -
+$STRIP --auto < "test/python_test.py" | diff "test/python_test.expected.py" -
+OUT="$(mktemp -d)"
+$STRIP test/rust_test.rs test/python_test.py --output-dir "$OUT"
+diff "test/rust_test.expected.rs" "$OUT/rust_test.rs"
+diff "test/python_test.expected.py" "$OUT/python_test.py"
+cp "test/c_test.c" "$OUT/in_place.c"
+$STRIP --c-style --in-place=.bak "$OUT/in_place.c"
+diff "test/c_test.expected.c" "$OUT/in_place.c"
+diff "test/c_test.c" "$OUT/in_place.c.bak"
+cp "test/c_test.c" "$OUT/short.c"
+$STRIP --c-style -i.orig "$OUT/short.c"
+diff "test/c_test.expected.c" "$OUT/short.c"
+diff "test/c_test.c" "$OUT/short.c.orig"
+printf 'int a; /* unterminated\n' > "$OUT/broken.c"
+$STRIP --c-style --in-place=.bak "$OUT/broken.c" 2>/dev/null && exit 1 || test $? -eq 2
+printf 'int a; /* unterminated\n' | diff - "$OUT/broken.c"
+test ! -e "$OUT/broken.c.bak"
+rm -r "$OUT"
+$STRIP --c-style --list "test/c_test.c" | diff "test/c_test.expected.list" -
+$STRIP --c-style --check "test/c_test.expected.c"
+$STRIP --c-style --check "test/c_test.c" 2>/dev/null && exit 1 || test $? -eq 1
+printf 'a; // b\n' | $STRIP --c-style --check 2>&1 >/dev/null | grep -q "^<stdin>: 1 comment$"
+mkdir "$OUT"
+printf 'a = 1 # b\n' > "$OUT/one.toml"
+$STRIP --auto --list "$OUT" 2>&1 >/dev/null | grep -q "^1 file checked, 1 with comments, 1 comment, 0 skipped, 0 failed$"
+rm -r "$OUT"
+$STRIP --c-style --extract --trim-delimiters --prefix "test/c_test.c" | diff "test/c_test.expected-extract.txt" -
+$STRIP --c-style --format ndjson "test/c_test.c" | diff "test/c_test.expected.ndjson" -
+$STRIP --style-file "test/lua_style.toml" "test/lua_test.lua" | diff "test/lua_test.expected.lua" -
+$STRIP --auto "test/go_test.go" | diff "test/go_test.expected.go" -
+$STRIP --auto "test/kotlin_test.kt" | diff "test/kotlin_test.expected.kt" -
+$STRIP --stream --auto "test/swift_test.swift" | diff "test/swift_test.expected.swift" -
+$STRIP --stream --style-file "test/lua_style.toml" "test/lua_test.lua" | diff "test/lua_test.expected.lua" -
+$STRIP --sql-style --sql-dialect postgresql "test/sql_test.sql" | diff "test/sql_test.expected.sql" -
+$STRIP --auto --sql-dialect postgresql --stream "test/sql_test.sql" | diff "test/sql_test.expected.sql" -
+$STRIP --toml-style "test/toml_test.toml" | diff "test/toml_test.expected.toml" -
+$STRIP --auto "test/ini_test.ini" | diff "test/ini_test.expected.ini" -
+$STRIP --stream --auto "test/properties_test.properties" | diff "test/properties_test.expected.properties" -
+$STRIP -B --yaml-style "test/yaml_test.yaml" | diff "test/yaml_test.expected.yaml" -
+$STRIP -B --stream --auto "test/yaml_test.yaml" | diff "test/yaml_test.expected.yaml" -
+$STRIP --jsonc-style --remove-trailing-commas "test/jsonc_test.jsonc" | diff "test/jsonc_test.expected.json" -
+$STRIP --stream --auto --remove-trailing-commas "test/jsonc_test.jsonc" | diff "test/jsonc_test.expected.json" -
+$STRIP --jsonc-style "test/jsonc_test.jsonc" 2>&1 >/dev/null | grep -q "jsonc_test.jsonc:7:29: error: invalid JSON"
+$STRIP --html-style "test/html_test.html" | diff "test/html_test.expected.html" -
+$STRIP --stream --auto "test/html_test.html" | diff "test/html_test.expected.html" -
+$STRIP --xml-style "test/xml_markup_test.xml" | diff "test/xml_markup_test.expected.xml" -
+$STRIP --stream --auto "test/xml_markup_test.xml" | diff "test/xml_markup_test.expected.xml" -
+printf '<a/>\n<!-- a -- b -->\n' | $STRIP --xml-style 2>&1 >/dev/null | grep -q "<stdin>:2:8: error: \`--\` inside comment"
+$STRIP --javascript-style "test/javascript_test.jsx" | diff "test/javascript_test.expected.jsx" -
+$STRIP --stream --auto "test/javascript_test.jsx" | diff "test/javascript_test.expected.jsx" -
+$STRIP --auto --keep-license-comments "test/javascript_test.jsx" | diff "test/javascript_test.expected-license.jsx" -
+$STRIP --auto "test/tsx_test.tsx" | diff "test/tsx_test.expected.tsx" -
+$STRIP --stream --tsx-style "test/tsx_test.tsx" | diff "test/tsx_test.expected.tsx" -
+$STRIP --auto "test/css_test.css" | diff "test/css_test.expected.css" -
+$STRIP --scss-style "test/scss_test.scss" | diff "test/scss_test.expected.scss" -
+$STRIP --stream --auto "test/scss_test.scss" | diff "test/scss_test.expected.scss" -
+$STRIP --auto --keep-license-comments "test/scss_test.scss" | diff "test/scss_test.expected-license.scss" -
@@ -0,0 +1,15 @@
+//! Crate level docs
+use std::fmt;
+/// A wrapper around a str
+struct Wrapper<'a> {
+    inner: &'a str, 
+}
+impl<'a> fmt::Display for Wrapper<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let quote = '"'; 
+        let slash = '/';
+        let raw = r#"// not a comment "# ;
+        let bytes = br"/* not a comment */";
+        write!(f, "{}{}{}{:?}{}", quote, slash, raw, bytes, self.inner) /** doc */
+    }
+}
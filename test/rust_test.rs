@@ -0,0 +1,18 @@
+//! Crate level docs
+use std::fmt;
+
+/// A wrapper around a str
+struct Wrapper<'a> {
+    inner: &'a str, // the wrapped str
+}
+
+/* outer /* nested */ still a comment */
+impl<'a> fmt::Display for Wrapper<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let quote = '"'; // not a string start
+        let slash = '/';
+        let raw = r#"// not a comment "# ;
+        let bytes = br"/* not a comment */";
+        write!(f, "{}{}{}{:?}{}", quote, slash, raw, bytes, self.inner) /** doc */
+    }
+}
@@ -0,0 +1,12 @@
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY, 
+    name TEXT NOT NULL DEFAULT 'it''s -- not a comment',
+    "weird -- column" INT  DEFAULT 1-1
+);
+CREATE FUNCTION greet(who TEXT) RETURNS TEXT AS $body$
+BEGIN
+    -- stays, inside the dollar quoted body
+    RETURN 'hello ' || who || $$ /* not a comment */ $$;
+END;
+$body$ LANGUAGE plpgsql;
+SELECT /*+ SeqScan(users) */ name FROM users WHERE id = $1; 
@@ -0,0 +1,16 @@
+-- create the schema
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY, -- surrogate key
+    name TEXT NOT NULL DEFAULT 'it''s -- not a comment',
+    "weird -- column" INT /* inline */ DEFAULT 1-1
+);
+
+/* a /* nested */ block comment */
+CREATE FUNCTION greet(who TEXT) RETURNS TEXT AS $body$
+BEGIN
+    -- stays, inside the dollar quoted body
+    RETURN 'hello ' || who || $$ /* not a comment */ $$;
+END;
+$body$ LANGUAGE plpgsql;
+
+SELECT /*+ SeqScan(users) */ name FROM users WHERE id = $1; -- parameter
@@ -0,0 +1,5 @@
+let s = #"raw \" // not a comment"# 
+let m = """
+    multi " // not a comment
+    """ 
+print(s, m, "// not either")
@@ -0,0 +1,6 @@
+/* Outer /* nested */ still a comment */
+let s = #"raw \" // not a comment"# // a comment
+let m = """
+    multi " // not a comment
+    """ /* block */
+print(s, m, "// not either")
@@ -0,0 +1,5 @@
+const identity = <T,>(x: T) => x; 
+const url = "http://example.org"; 
+function wrap<T extends object = {}>(x: T) {
+    return <div title="// no">{}x // text</div>;
+}
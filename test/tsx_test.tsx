@@ -0,0 +1,6 @@
+// Generic helpers
+const identity = <T,>(x: T) => x; // not JSX
+const url = "http://example.org"; // after the generic
+function wrap<T extends object = {}>(x: T) {
+    return <div title="// no">{/* child */}x // text</div>;
+}
@@ -0,0 +1,7 @@
+/* Überschrift: Grüße aus München */
+#include <stdio.h>
+int main() {
+    // 日本語のコメント
+    printf("héllo wörld // кириллица\n"); /* 🦀 emoji */
+    char *s = "/* 中文 */"; // ñ
+}
@@ -0,0 +1,6 @@
+#include <stdio.h>
+int main() {
+    
+    printf("héllo wörld // кириллица\n"); 
+    char *s = "/* 中文 */"; 
+}
@@ -0,0 +1,3 @@
+#!/bin/sh 
+echo "Ünïcödé #not a comment" 
+echo 'naïve' 
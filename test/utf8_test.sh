@@ -0,0 +1,4 @@
+#!/bin/sh # Schöne Grüße
+# ユーティリティ
+echo "Ünïcödé #not a comment" # 🐚 comment
+echo 'naïve' #ß